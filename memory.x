@@ -5,7 +5,12 @@ MEMORY {
      * has, but your board may have more or less Flash and you should adjust
      * this value to suit.
      */
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 64K
+    /*
+     * The last 64 KiB of Flash are reserved for the settings store
+     * (SETTINGS_OFFSET / SETTINGS_SIZE in main.rs). Nothing is linked there.
+     */
+    SETTINGS : ORIGIN = 0x101F0000, LENGTH = 64K
     /*
      * RAM consists of 4 banks, SRAM0-SRAM3, with a striped mapping.
      * This is usually good for performance, as it distributes load on
@@ -0,0 +1,137 @@
+pub mod bits {
+
+    pub enum BitOp {
+        Write,  // b3 1, [7:0] 0x42
+        Set,    // s3, s[7:0]
+        Clear,  // c3, c[7:0]
+        Toggle, // t15, t[7:0]
+    }
+
+    // parse a 16 bit value given as decimal, hex (0x..) or binary (0b..)
+    pub fn parse_value(text: &str) -> Option<i16> {
+        let text = text.trim();
+        if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+            u16::from_str_radix(hex, 16).ok().map(|v| v as i16)
+        } else if let Some(bin) = text.strip_prefix("0b").or(text.strip_prefix("0B")) {
+            u16::from_str_radix(bin, 2).ok().map(|v| v as i16)
+        } else if let Ok(v) = text.parse::<i16>() {
+            Some(v)
+        } else {
+            // allow unsigned decimals up to 65535
+            text.parse::<u16>().ok().map(|v| v as i16)
+        }
+    }
+
+    // parse a single bit "3" or "[3]" or a bit range "[7:0]" into (high bit, low bit)
+    pub fn parse_bit_range(text: &str) -> Option<(u8, u8)> {
+        let (hi, lo) = match text.strip_prefix('[') {
+            Some(range) => {
+                let range = range.strip_suffix(']')?;
+                let (hi, lo) = range.split_once(':').unwrap_or((range, range));
+                (hi.parse::<u8>().ok()?, lo.parse::<u8>().ok()?)
+            }
+            None => {
+                let bit = text.parse::<u8>().ok()?;
+                (bit, bit)
+            }
+        };
+        if hi > 15 || lo > hi {
+            return None;
+        }
+        Some((hi, lo))
+    }
+
+    // parse the input channel "in", "in[3]" or "in[7:0]" at the start of text
+    // into (high bit, low bit, rest of text), plain "in" is all 16 bits
+    pub fn parse_input_bits(text: &str) -> Option<(u8, u8, &str)> {
+        let rest = text.strip_prefix("in")?;
+        if !rest.starts_with('[') {
+            return Some((15, 0, rest));
+        }
+        let end = rest.find(']')? + 1;
+        let (hi, lo) = parse_bit_range(&rest[..end])?;
+        Some((hi, lo, &rest[end..]))
+    }
+
+    // parse a bit command token like "b3", "t15", "[7:0]" or "s[3:0]"
+    pub fn parse_bit_op(text: &str) -> Option<(BitOp, u8, u8)> {
+        let (op, range) = match text.as_bytes().first()? {
+            b'b' => (BitOp::Write, &text[1..]),
+            b's' => (BitOp::Set, &text[1..]),
+            b'c' => (BitOp::Clear, &text[1..]),
+            b't' => (BitOp::Toggle, &text[1..]),
+            b'[' => (BitOp::Write, text),
+            _ => return None,
+        };
+        let (hi, lo) = parse_bit_range(range)?;
+        Some((op, hi, lo))
+    }
+
+    // mask with all bits from hi down to lo set
+    pub fn bit_mask(hi: u8, lo: u8) -> u16 {
+        (u16::MAX >> (15 - hi)) & (u16::MAX << lo)
+    }
+
+    // apply a bit operation to data, None if value does not fit into the range
+    pub fn apply_bit_op(data: i16, op: &BitOp, hi: u8, lo: u8, value: i16) -> Option<i16> {
+        let mask = bit_mask(hi, lo);
+        let data = data as u16;
+        let result = match op {
+            BitOp::Write => {
+                let value = value as u16;
+                if value > mask >> lo {
+                    return None;
+                }
+                (data & !mask) | (value << lo)
+            }
+            BitOp::Set => data | mask,
+            BitOp::Clear => data & !mask,
+            BitOp::Toggle => data ^ mask,
+        };
+        Some(result as i16)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn bit_range_forms() {
+            assert_eq!(parse_bit_range("3"), Some((3, 3)));
+            assert_eq!(parse_bit_range("[15]"), Some((15, 15)));
+            assert_eq!(parse_bit_range("[7:0]"), Some((7, 0)));
+            assert_eq!(parse_bit_range("[15:15]"), Some((15, 15)));
+        }
+
+        #[test]
+        fn bit_range_invalid() {
+            assert_eq!(parse_bit_range("[0:7]"), None); // reversed
+            assert_eq!(parse_bit_range("16"), None);
+            assert_eq!(parse_bit_range("[16]"), None);
+            assert_eq!(parse_bit_range("[16:0]"), None);
+            assert_eq!(parse_bit_range("[7:0"), None);
+            assert_eq!(parse_bit_range("[]"), None);
+            assert_eq!(parse_bit_range("[a:0]"), None);
+        }
+
+        #[test]
+        fn input_bits() {
+            assert_eq!(parse_input_bits("in"), Some((15, 0, "")));
+            assert_eq!(parse_input_bits("in[3]"), Some((3, 3, "")));
+            assert_eq!(parse_input_bits("in[7:0] == 1"), Some((7, 0, " == 1")));
+            assert_eq!(parse_input_bits("in changes"), Some((15, 0, " changes")));
+            assert_eq!(parse_input_bits("in[16]"), None);
+            assert_eq!(parse_input_bits("in[3"), None);
+            assert_eq!(parse_input_bits("out[3]"), None);
+        }
+
+        #[test]
+        fn bit_op_with_single_bit_brackets() {
+            assert!(matches!(
+                parse_bit_op("t[15]"),
+                Some((BitOp::Toggle, 15, 15))
+            ));
+            assert!(matches!(parse_bit_op("[3]"), Some((BitOp::Write, 3, 3))));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+pub mod breakpoint {
+    use crate::bits::bits::{bit_mask, parse_input_bits, parse_value};
+    use core::fmt::{Display, Formatter, Result};
+    use heapless::Vec;
+
+    // Conditions on the input channel that halt the clocks and programs
+    //
+    //     in == 0x1234     in[15] == 1     in[7:0] != 3     in changes
+    //
+    // A comparison matches when the input starts to match, not while it stays,
+    // so a running program is halted once and can be continued. A condition
+    // that is already true when it is added halts the next time it becomes true.
+
+    pub const MAX_BREAKPOINTS: usize = 4;
+
+    pub enum Condition {
+        Equals { hi: u8, lo: u8, value: u16 },
+        NotEquals { hi: u8, lo: u8, value: u16 },
+        Changes,
+    }
+
+    impl Condition {
+        pub fn parse(text: &str) -> core::result::Result<Self, &'static str> {
+            let text = text.trim();
+            if !text.starts_with("in") {
+                return Err("Err: breakpoint needs in, in[n] or in[h:l]");
+            }
+            let (hi, lo, rest) = parse_input_bits(text).ok_or("Err: no valid bit range found")?;
+            let rest = rest.trim();
+            if rest == "changes" {
+                return Ok(Condition::Changes);
+            }
+            let (equals, value) = if let Some(value) = rest.strip_prefix("==") {
+                (true, value)
+            } else if let Some(value) = rest.strip_prefix("!=") {
+                (false, value)
+            } else {
+                return Err("Err: breakpoint needs ==, != or changes");
+            };
+            let value = parse_value(value).ok_or("Err: no valid value found")? as u16;
+            if value > bit_mask(hi, lo) >> lo {
+                return Err("Err: value does not fit into the bit range");
+            }
+            Ok(match equals {
+                true => Condition::Equals { hi, lo, value },
+                false => Condition::NotEquals { hi, lo, value },
+            })
+        }
+
+        // the comparison is true for this input
+        fn holds(&self, data: u16) -> bool {
+            let field = |hi: u8, lo: u8| (data & bit_mask(hi, lo)) >> lo;
+            match *self {
+                Condition::Equals { hi, lo, value } => field(hi, lo) == value,
+                Condition::NotEquals { hi, lo, value } => field(hi, lo) != value,
+                Condition::Changes => false,
+            }
+        }
+
+        fn matches(&self, previous: u16, data: u16) -> bool {
+            match self {
+                Condition::Changes => data != previous,
+                _ => self.holds(data) && !self.holds(previous),
+            }
+        }
+    }
+
+    impl Display for Condition {
+        fn fmt(&self, f: &mut Formatter) -> Result {
+            let (hi, lo, value, op) = match *self {
+                Condition::Equals { hi, lo, value } => (hi, lo, value, "=="),
+                Condition::NotEquals { hi, lo, value } => (hi, lo, value, "!="),
+                Condition::Changes => return write!(f, "in changes"),
+            };
+            match (hi, lo) {
+                (15, 0) => write!(f, "in {} 0x{:04X}", op, value),
+                _ if hi == lo => write!(f, "in[{}] {} {}", hi, op, value),
+                _ => write!(f, "in[{}:{}] {} 0x{:X}", hi, lo, op, value),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    pub struct Breakpoints {
+        conditions: Vec<Condition, MAX_BREAKPOINTS>,
+        previous: Option<u16>, // input data of the last check
+    }
+
+    impl Breakpoints {
+        pub fn new() -> Self {
+            Breakpoints {
+                conditions: Vec::new(),
+                previous: None,
+            }
+        }
+
+        // data is the current input, the next change is compared against it;
+        // returns the number of the breakpoint and if it is already true
+        pub fn add(
+            &mut self,
+            condition: Condition,
+            data: i16,
+        ) -> core::result::Result<(usize, bool), &'static str> {
+            let holds = condition.holds(data as u16);
+            self.conditions
+                .push(condition)
+                .map_err(|_| "Err: max 4 breakpoints")?;
+            self.previous = Some(data as u16);
+            Ok((self.conditions.len(), holds))
+        }
+
+        // remove breakpoint number n (from 1) or all
+        pub fn clear(&mut self, number: Option<usize>) -> core::result::Result<(), &'static str> {
+            match number {
+                Some(n) if n >= 1 && n <= self.conditions.len() => {
+                    self.conditions.remove(n - 1);
+                }
+                Some(_) => return Err("Err: no valid breakpoint number found"),
+                None => self.conditions.clear(),
+            }
+            Ok(())
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = &Condition> {
+            self.conditions.iter()
+        }
+
+        // number (from 1) of the first breakpoint matching the new input data
+        pub fn check(&mut self, data: i16) -> Option<usize> {
+            let data = data as u16;
+            let previous = self.previous.replace(data)?;
+            self.conditions
+                .iter()
+                .position(|c| c.matches(previous, data))
+                .map(|i| i + 1)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn parse(text: &str) -> Condition {
+            Condition::parse(text).unwrap_or_else(|e| panic!("{}: {}", text, e))
+        }
+
+        #[test]
+        fn parses_the_documented_forms() {
+            assert!(matches!(
+                parse("in == 0x1234"),
+                Condition::Equals {
+                    hi: 15,
+                    lo: 0,
+                    value: 0x1234
+                }
+            ));
+            assert!(matches!(
+                parse("in[15] == 1"),
+                Condition::Equals {
+                    hi: 15,
+                    lo: 15,
+                    value: 1
+                }
+            ));
+            assert!(matches!(
+                parse("in[7:0] != 3"),
+                Condition::NotEquals {
+                    hi: 7,
+                    lo: 0,
+                    value: 3
+                }
+            ));
+            assert!(matches!(parse("in changes"), Condition::Changes));
+            assert!(Condition::parse("in[3] == 2").is_err()); // does not fit
+            assert!(Condition::parse("out == 1").is_err());
+        }
+
+        #[test]
+        fn matches_on_the_edge() {
+            let mut breakpoints = Breakpoints::new();
+            breakpoints.add(parse("in[15] == 1"), 0).unwrap();
+            assert_eq!(breakpoints.check(0x8000u16 as i16), Some(1));
+            assert_eq!(breakpoints.check(0x8001u16 as i16), None); // stays true
+            assert_eq!(breakpoints.check(0), None);
+            assert_eq!(breakpoints.check(0x8000u16 as i16), Some(1));
+        }
+
+        #[test]
+        fn first_change_after_adding_is_seen() {
+            let mut breakpoints = Breakpoints::new();
+            breakpoints.add(parse("in changes"), 5).unwrap();
+            assert_eq!(breakpoints.check(6), Some(1));
+        }
+
+        #[test]
+        fn already_true_when_added() {
+            let mut breakpoints = Breakpoints::new();
+            assert_eq!(breakpoints.add(parse("in == 7"), 7), Ok((1, true)));
+            assert_eq!(breakpoints.check(8), None);
+            assert_eq!(breakpoints.check(7), Some(1));
+        }
+    }
+}
@@ -4,9 +4,12 @@ pub mod button {
     use panic_halt as _;
     use rp2040_hal::gpio::{DynPinId, FunctionSioInput, Pin, PullDown};
 
+    const LONG_PRESS_TICKS: u64 = 600_000; // 600ms
+
     pub struct ButtonState {
         pub state: bool,
         pub state_changed: bool,
+        pub long_press: bool, // held for LONG_PRESS_TICKS, reported once
     }
 
     pub struct Button {
@@ -15,6 +18,8 @@ pub mod button {
         update_interval: u64,
         debounce: u8,
         next_tick: u64,
+        pressed_at: u64,
+        long_reported: bool,
     }
 
     impl Button {
@@ -25,6 +30,8 @@ pub mod button {
                 update_interval: 1000,
                 next_tick: 0,
                 debounce: 0,
+                pressed_at: 0,
+                long_reported: false,
             }
         }
 
@@ -47,9 +54,19 @@ pub mod button {
                 }
                 self.next_tick = now + self.update_interval;
             }
+            let mut long_press = false;
+            if state_changed && self.state {
+                self.pressed_at = now;
+                self.long_reported = false;
+            } else if self.state && !self.long_reported && now > self.pressed_at + LONG_PRESS_TICKS
+            {
+                self.long_reported = true;
+                long_press = true;
+            }
             ButtonState {
                 state: self.state,
                 state_changed,
+                long_press,
             }
         }
     }
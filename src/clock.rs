@@ -4,50 +4,124 @@ pub mod clock {
 
     use embedded_hal::digital::OutputPin;
     use panic_halt as _;
-    use rp2040_hal::gpio::{DynPinId, FunctionSioOutput, Pin, PullDown};
+    use rp2040_hal::gpio::{DynPinId, FunctionPio0, FunctionSioOutput, Pin, PullDown};
 
     use crate::button::button::ButtonState;
+    use crate::frequency::frequency::Frequency;
     pub enum ClockMode {
         Manual,
         Auto,
+        Burst, // auto for burst_length pulses, then manual
+    }
+
+    //     CLOCK:  ‾‾‾‾‾‾‾‾|________________|‾‾‾‾‾‾‾‾|____
+    //             high_ticks  low_ticks
+    //             |<- period_ticks ->|
+    //
+    // duty is the high part of the period in %
+    //
+    // With hardware set an auto clock may be generated by a PIO state machine
+    // (see pio_clock), the pin is then switched to the PIO and update leaves it
+    // alone.
+
+    // offset of a clock to the other clock
+    pub enum Phase {
+        Degrees(u16),
+        Micros(u64),
     }
 
     pub struct Clock {
-        pin: Pin<DynPinId, FunctionSioOutput, PullDown>,
-        ticks_per_step: u64,
+        pin: Option<Pin<DynPinId, FunctionSioOutput, PullDown>>,
+        hw_pin: Option<Pin<DynPinId, FunctionPio0, PullDown>>, // pin while the PIO drives it
+        period_ticks: u64,
+        high_ticks: u64,
+        low_ticks: u64,
+        last_rise: u64, // start of the current period
         pub mode: ClockMode,
         pub next_tick: u64,
         pub state: bool,
-        pub freq: u64,
+        pub freq: Frequency,
+        pub duty: u8,
+        pub hardware: bool, // hardware generation requested
+        burst_length: u32,
+        burst_done: u32,
+        pulse_end: Option<u64>, // end of a manual pulse from the terminal
+        level: bool,            // level last driven in software
+        cycles: u64,            // rising edges
+        hw_start: Option<(u64, Frequency)>, // PIO generated since, at frequency
+        last_update: u64,
+        gated_since: Option<u64>, // auto clock paused by the gate bit
     }
 
     impl Clock {
         pub fn new(pin: Pin<DynPinId, FunctionSioOutput, PullDown>, now: u64) -> Self {
             let mut cl = Clock {
-                pin,
-                ticks_per_step: 1000,
+                pin: Some(pin),
+                hw_pin: None,
+                period_ticks: 2000,
+                high_ticks: 1000,
+                low_ticks: 1000,
+                last_rise: now,
                 mode: ClockMode::Manual,
                 next_tick: now,
                 state: false,
-                freq: 10,
+                freq: Frequency::from_hertz(1),
+                duty: 50,
+                hardware: false,
+                burst_length: 0,
+                burst_done: 0,
+                pulse_end: None,
+                level: false,
+                cycles: 0,
+                hw_start: None,
+                last_update: now,
+                gated_since: None,
             };
             cl.set_ticks_per_step();
             cl
         }
 
-        pub fn update(&mut self, now: u64, button_state: &ButtonState) -> bool {
+        // gated pauses auto and burst mode, the periods continue afterwards
+        pub fn update(&mut self, now: u64, button_state: &ButtonState, gated: bool) -> bool {
             let mut changed = false;
+            self.last_update = now;
+            match (gated, self.gated_since) {
+                (true, None) => self.gated_since = Some(now),
+                (false, Some(since)) => {
+                    self.gated_since = None;
+                    self.next_tick += now - since;
+                }
+                _ => {}
+            }
             match self.mode {
                 ClockMode::Manual => {
                     if button_state.state_changed {
                         self.state = button_state.state;
+                        self.pulse_end = None;
+                        changed = true;
+                    } else if self.pulse_end.is_some_and(|end| now >= end) {
+                        self.state = false;
+                        self.pulse_end = None;
                         changed = true;
                     }
                 }
-                ClockMode::Auto => {
-                    if now > self.next_tick {
+                ClockMode::Auto if self.is_hardware() => {}
+                ClockMode::Auto | ClockMode::Burst => {
+                    if now > self.next_tick && !gated {
                         self.state = !self.state;
-                        self.next_tick += self.ticks_per_step;
+                        if self.state {
+                            self.last_rise = self.next_tick;
+                            self.next_tick += self.high_ticks;
+                            self.burst_done += 1;
+                        } else {
+                            self.next_tick += self.low_ticks;
+                            // the last pulse of a burst ends with its falling edge
+                            if matches!(self.mode, ClockMode::Burst)
+                                && self.burst_done >= self.burst_length
+                            {
+                                self.mode = ClockMode::Manual;
+                            }
+                        }
                         changed = true;
                     }
                 }
@@ -58,9 +132,84 @@ pub mod clock {
             self.state
         }
 
-        pub fn set_freq(&mut self, interval: &u64) {
-            self.freq = *interval;
+        pub fn set_freq(&mut self, freq: Frequency) {
+            self.freq = freq;
+            self.set_ticks_per_step();
+        }
+
+        // frequency of the software clock after rounding the period to ticks
+        pub fn achieved_freq(&self) -> Frequency {
+            Frequency::from_period_ticks(self.period_ticks, SYS_TICKS)
+        }
+
+        pub fn set_duty(&mut self, duty: u8) -> Result<(), &'static str> {
+            if duty == 0 || duty >= 100 {
+                return Err("Err: duty cycle needs 1-99 %");
+            }
+            self.duty = duty;
             self.set_ticks_per_step();
+            Ok(())
+        }
+
+        // start the next period offset to the periods of the reference clock,
+        // only a running software clock has rises to refer to
+        pub fn set_phase(
+            &mut self,
+            reference: &Clock,
+            phase: &Phase,
+            now: u64,
+        ) -> Result<(), &'static str> {
+            if !matches!(reference.mode, ClockMode::Auto)
+                || reference.is_hardware()
+                || reference.gated_since.is_some()
+            {
+                return Err("Err: phase needs the other clock running in software");
+            }
+            let period = reference.period_ticks;
+            let offset = match phase {
+                Phase::Degrees(degrees) => period * *degrees as u64 / 360,
+                Phase::Micros(micros) => micros * SYS_TICKS / 1_000_000,
+            };
+            if offset >= period {
+                return Err("Err: phase must be shorter than the period of the other clock");
+            }
+            // the first offset rise of the reference after now
+            let mut rise = reference.last_rise + offset;
+            if rise <= now {
+                rise += ((now - rise) / period + 1) * period;
+            }
+            self.state = false;
+            self.set_pin(false);
+            self.next_tick = rise;
+            Ok(())
+        }
+
+        // drive a manual clock like its button does
+        pub fn set_level(&mut self, state: bool) {
+            self.state = state;
+            self.pulse_end = None;
+            self.set_pin(state);
+        }
+
+        // high now, low again after micros
+        pub fn start_pulse(&mut self, micros: u64, now: u64) {
+            self.set_level(true);
+            self.pulse_end = Some(now + micros * SYS_TICKS / 1_000_000);
+        }
+
+        // emit pulses periods starting now, then return to manual mode
+        pub fn start_burst(&mut self, pulses: u32, now: u64) {
+            self.mode = ClockMode::Burst;
+            self.burst_length = pulses;
+            self.burst_done = 0;
+            self.state = false;
+            self.set_pin(false);
+            self.next_tick = now;
+        }
+
+        // pulses emitted, pulses of the burst
+        pub fn get_burst_progress(&self) -> (u32, u32) {
+            (self.burst_done, self.burst_length)
         }
 
         pub fn sync(&mut self, clock2: &mut Clock) {
@@ -70,18 +219,69 @@ pub mod clock {
 
         pub fn sync_opposite(&mut self, clock2: &mut Clock) {
             clock2.state = self.state;
-            clock2.next_tick = self.next_tick + self.ticks_per_step;
+            clock2.next_tick = self.next_tick + self.period_ticks / 2;
         }
 
+        // also used by programs, counts the rising edges
         pub fn set_pin(&mut self, state: bool) {
+            let Some(pin) = self.pin.as_mut() else {
+                return; // driven by the PIO
+            };
+            if state && !self.level {
+                self.cycles += 1;
+            }
+            self.level = state;
             if state {
-                self.pin.set_high().unwrap();
+                pin.set_high().unwrap();
             } else {
-                self.pin.set_low().unwrap();
+                pin.set_low().unwrap();
+            }
+        }
+
+        // hand the pin to the PIO state machine running at freq or take it back
+        pub fn use_hardware(&mut self, freq: Option<Frequency>) {
+            self.cycles = self.get_cycles(self.last_update);
+            self.hw_start = freq.map(|f| (self.last_update, f));
+            if freq.is_some() {
+                if let Some(pin) = self.pin.take() {
+                    // both clock pins have a PIO function
+                    self.hw_pin = pin.try_into_function().ok();
+                }
+            } else if let Some(pin) = self.hw_pin.take() {
+                self.pin = pin.try_into_function().ok();
+                self.level = self.state; // no edge of its own
+                self.set_pin(self.state);
             }
         }
+
+        // rising edges since the last reset, the edges of the PIO can not be
+        // seen and are calculated from the time it runs
+        pub fn get_cycles(&self, now: u64) -> u64 {
+            match self.hw_start {
+                Some((start, freq)) => {
+                    let elapsed = now.saturating_sub(start) as u128;
+                    let edges = elapsed * freq.millihertz() as u128 / (SYS_TICKS as u128 * 1000);
+                    self.cycles + edges as u64
+                }
+                None => self.cycles,
+            }
+        }
+
+        pub fn reset_cycles(&mut self) {
+            self.cycles = 0;
+            if let Some((start, _)) = self.hw_start.as_mut() {
+                *start = self.last_update;
+            }
+        }
+
+        pub fn is_hardware(&self) -> bool {
+            self.hw_pin.is_some()
+        }
+
         fn set_ticks_per_step(&mut self) {
-            self.ticks_per_step = SYS_TICKS * 10 / (self.freq * 2);
+            self.period_ticks = self.freq.period_ticks(SYS_TICKS).max(2);
+            self.high_ticks = (self.period_ticks * self.duty as u64 / 100).max(1);
+            self.low_ticks = self.period_ticks.saturating_sub(self.high_ticks).max(1);
         }
     }
 }
@@ -0,0 +1,194 @@
+pub mod clock_calc {
+    // Decides how a clock is generated and computes the PIO timing. Nothing in
+    // here touches hardware, so the decisions can be checked on the host.
+    //
+    // The PIO program (see pio_clock) spends HIGH_OVERHEAD cycles of the high
+    // part and LOW_OVERHEAD cycles of the low part on its own instructions, the
+    // rest are 16 bit loop counts. A fractional clock divider stretches longer
+    // periods until the counts fit.
+
+    use crate::frequency::frequency::Frequency;
+
+    pub const HIGH_OVERHEAD: u64 = 3; // set, out and the last jmp
+    pub const LOW_OVERHEAD: u64 = 5; // set, pull, mov, out and the last jmp
+    const MAX_CYCLES: u64 = u16::MAX as u64; // cycles per period after the divider
+    pub const MIN_FREQ: Frequency = Frequency::from_millihertz(1);
+    pub const SOFTWARE_MAX_FREQ: Frequency = Frequency::from_hertz(1_000); // main loop toggling
+    pub const HARDWARE_MIN_FREQ: Frequency = Frequency::from_hertz(10); // slower is exact enough in software
+
+    // highest frequency the PIO program can produce
+    pub fn hardware_max_freq(sys_clk_hz: u32) -> Frequency {
+        Frequency::from_hertz(sys_clk_hz as u64 / (HIGH_OVERHEAD + LOW_OVERHEAD))
+    }
+
+    // valid frequencies of a clock with or without hardware generation
+    pub fn check_freq(
+        sys_clk_hz: u32,
+        freq: Frequency,
+        hardware: bool,
+    ) -> Result<(), &'static str> {
+        let max = if hardware {
+            hardware_max_freq(sys_clk_hz)
+        } else {
+            SOFTWARE_MAX_FREQ
+        };
+        freq.check_range(MIN_FREQ, max).map_err(|e| match hardware {
+            false if freq > max => "Err: max 1 kHz for a software clock, try 'c1/2 hw on'",
+            _ => e,
+        })
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct PioTiming {
+        pub clkdiv_int: u16,
+        pub clkdiv_frac: u8,
+        pub high_count: u16,
+        pub low_count: u16,
+    }
+
+    impl PioTiming {
+        // both counts in one FIFO word, high count in the low half
+        pub fn packed(&self) -> u32 {
+            (self.low_count as u32) << 16 | self.high_count as u32
+        }
+
+        // frequency the state machine really produces
+        pub fn achieved_freq(&self, sys_clk_hz: u32) -> Frequency {
+            let cycles =
+                self.high_count as u64 + HIGH_OVERHEAD + self.low_count as u64 + LOW_OVERHEAD;
+            let divider = self.clkdiv_int as u64 * 256 + self.clkdiv_frac as u64;
+            Frequency::from_period_ticks(cycles * divider, sys_clk_hz as u64 * 256)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum ClockSource {
+        Software,
+        Hardware(PioTiming),
+    }
+
+    // hardware if it was asked for and the clock is fast enough, else software
+    pub fn plan(
+        sys_clk_hz: u32,
+        freq: Frequency,
+        duty: u8,
+        hardware: bool,
+    ) -> Result<ClockSource, &'static str> {
+        if !hardware || freq < HARDWARE_MIN_FREQ {
+            return Ok(ClockSource::Software);
+        }
+
+        // period in 1/256 system cycles, the unit of the clock divider
+        let period = freq.period_ticks(sys_clk_hz as u64 * 256);
+        let divider = period.div_ceil(MAX_CYCLES).max(256);
+        if divider > u16::MAX as u64 * 256 {
+            return Err("Err: frequency too low for the hardware clock");
+        }
+        let cycles = (period + divider / 2) / divider;
+        if cycles < HIGH_OVERHEAD + LOW_OVERHEAD {
+            return Err("Err: frequency too high for the hardware clock");
+        }
+
+        let high = (cycles * duty as u64 + 50) / 100;
+        let high = high.clamp(HIGH_OVERHEAD, cycles - LOW_OVERHEAD);
+        Ok(ClockSource::Hardware(PioTiming {
+            clkdiv_int: (divider / 256) as u16,
+            clkdiv_frac: (divider % 256) as u8,
+            high_count: (high - HIGH_OVERHEAD) as u16,
+            low_count: (cycles - high - LOW_OVERHEAD) as u16,
+        }))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const SYS: u32 = 125_000_000;
+
+        fn hardware(freq: Frequency, duty: u8) -> PioTiming {
+            match plan(SYS, freq, duty, true) {
+                Ok(ClockSource::Hardware(timing)) => timing,
+                other => panic!("no hardware timing: {:?}", other),
+            }
+        }
+
+        fn cycles(t: &PioTiming) -> u64 {
+            t.high_count as u64 + HIGH_OVERHEAD + t.low_count as u64 + LOW_OVERHEAD
+        }
+
+        #[test]
+        fn software_unless_asked_and_fast_enough() {
+            let freq = Frequency::from_hertz(100);
+            assert_eq!(plan(SYS, freq, 50, false), Ok(ClockSource::Software));
+            let slow = Frequency::from_millihertz(9_999);
+            assert_eq!(plan(SYS, slow, 50, true), Ok(ClockSource::Software));
+        }
+
+        #[test]
+        fn split_includes_the_overhead() {
+            // 125 cycles, 63 of them high
+            let t = hardware(Frequency::from_hertz(1_000_000), 50);
+            assert_eq!((t.clkdiv_int, t.clkdiv_frac), (1, 0));
+            assert_eq!((t.high_count, t.low_count), (63 - 3, 125 - 63 - 5));
+            assert_eq!(t.achieved_freq(SYS), Frequency::from_hertz(1_000_000));
+            assert_eq!(t.packed(), (57 << 16) | 60);
+        }
+
+        #[test]
+        fn duty_is_limited_by_the_overhead() {
+            let t = hardware(Frequency::from_hertz(1_000_000), 1);
+            assert_eq!(
+                (t.high_count, t.low_count),
+                (0, 125 - HIGH_OVERHEAD as u16 - 5)
+            );
+            let t = hardware(Frequency::from_hertz(1_000_000), 99);
+            assert_eq!(
+                (t.high_count, t.low_count),
+                (125 - 3 - LOW_OVERHEAD as u16, 0)
+            );
+        }
+
+        #[test]
+        fn fastest_and_too_fast() {
+            let max = hardware_max_freq(SYS);
+            assert_eq!(max, Frequency::from_hertz(15_625_000));
+            let t = hardware(max, 50);
+            assert_eq!((t.high_count, t.low_count), (0, 0));
+            assert_eq!(t.achieved_freq(SYS), max);
+            let too_fast = Frequency::from_hertz(20_000_000);
+            assert!(plan(SYS, too_fast, 50, true).is_err());
+            assert!(check_freq(SYS, too_fast, true).is_err());
+        }
+
+        #[test]
+        fn slow_clocks_use_the_divider() {
+            let freq = Frequency::from_hertz(10);
+            let t = hardware(freq, 50);
+            assert!(t.clkdiv_int > 1);
+            assert!(cycles(&t) <= MAX_CYCLES);
+            let achieved = t.achieved_freq(SYS).millihertz();
+            assert!(achieved.abs_diff(freq.millihertz()) <= freq.millihertz() / 1000);
+        }
+
+        #[test]
+        fn slowest_hardware_clock_fits_the_divider() {
+            // slower clocks stay in software, so the divider limit is never hit
+            for sys in [SYS, 133_000_000, u32::MAX] {
+                match plan(sys, HARDWARE_MIN_FREQ, 50, true) {
+                    Ok(ClockSource::Hardware(t)) => assert!(cycles(&t) <= MAX_CYCLES),
+                    other => panic!("no hardware timing at {}: {:?}", sys, other),
+                }
+            }
+        }
+
+        #[test]
+        fn frequency_range_per_source() {
+            let k2 = Frequency::from_hertz(2_000);
+            assert!(check_freq(SYS, k2, false).is_err());
+            assert_eq!(check_freq(SYS, k2, true), Ok(()));
+            assert_eq!(check_freq(SYS, SOFTWARE_MAX_FREQ, false), Ok(()));
+            assert_eq!(check_freq(SYS, MIN_FREQ, false), Ok(()));
+            assert!(check_freq(SYS, Frequency::from_millihertz(0), false).is_err());
+        }
+    }
+}
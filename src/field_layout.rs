@@ -0,0 +1,211 @@
+pub mod field_layout {
+    use crate::bits::bits::{apply_bit_op, bit_mask, parse_value, BitOp};
+    use crate::format_str::format_str::PAGE_STR_WIDTH;
+    use crate::storage::storage::{Persist, Sink, Source, StorageError};
+    use core::fmt::Write;
+    use heapless::{String, Vec};
+
+    pub const MAX_FIELDS: usize = 16; // Maximum number of fields per channel
+    pub const FIELD_NAME_WIDTH: usize = 8;
+    pub const FIELD_TEXT_WIDTH: usize = 37; // width of decoded text column
+
+    pub struct Field {
+        pub name: String<FIELD_NAME_WIDTH>,
+        pub hi: u8,
+        pub lo: u8,
+    }
+
+    #[derive(Default)]
+    pub struct FieldLayout {
+        fields: Vec<Field, MAX_FIELDS>,
+    }
+
+    impl FieldLayout {
+        pub fn new() -> Self {
+            FieldLayout { fields: Vec::new() }
+        }
+
+        // parse definitions like "zx:15" (single bit) or "out:0-13" (bit range)
+        pub fn parse(definitions: &[String<PAGE_STR_WIDTH>]) -> Result<Self, &'static str> {
+            let mut layout = FieldLayout::new();
+            for def in definitions {
+                let (name, bits) = def.split_once(':').ok_or("Err: field needs name:bit")?;
+                if name.is_empty() || name.contains('=') {
+                    return Err("Err: no valid field name found");
+                }
+                let name = String::try_from(name).map_err(|_| "Err: field name too long")?;
+                let (a, b) = match bits.split_once('-') {
+                    Some((a, b)) => (a.parse::<u8>(), b.parse::<u8>()),
+                    None => (bits.parse::<u8>(), bits.parse::<u8>()),
+                };
+                let (Ok(a), Ok(b)) = (a, b) else {
+                    return Err("Err: no valid field bits found");
+                };
+                let (hi, lo) = (a.max(b), a.min(b));
+                if hi > 15 {
+                    return Err("Err: field bits must be 0-15");
+                }
+                if layout.find(&name).is_some() {
+                    return Err("Err: duplicate field name");
+                }
+                if layout
+                    .fields
+                    .iter()
+                    .any(|f| bit_mask(f.hi, f.lo) & bit_mask(hi, lo) != 0)
+                {
+                    return Err("Err: fields overlap");
+                }
+                layout
+                    .fields
+                    .push(Field { name, hi, lo })
+                    .map_err(|_| "Err: too many fields")?;
+            }
+            Ok(layout)
+        }
+
+        pub fn find(&self, name: &str) -> Option<&Field> {
+            self.fields.iter().find(|f| f.name.as_str() == name)
+        }
+
+        // apply assignments like "f=1" or "out=0x3FF" to data
+        pub fn assign(
+            &self,
+            data: i16,
+            assignments: &[String<PAGE_STR_WIDTH>],
+        ) -> Result<i16, &'static str> {
+            if self.is_empty() {
+                return Err("Err: no fields defined for channel");
+            }
+            let mut data = data;
+            for assignment in assignments {
+                let (name, value) = assignment
+                    .split_once('=')
+                    .ok_or("Err: field assignment needs name=value")?;
+                let field = self.find(name).ok_or("Err: unknown field name")?;
+                let value = parse_value(value).ok_or("no valid 16bit number found")?;
+                data = apply_bit_op(data, &BitOp::Write, field.hi, field.lo, value)
+                    .ok_or("Err: value does not fit into field")?;
+            }
+            Ok(data)
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.fields.is_empty()
+        }
+
+        // decoded field values, e.g. "zx=1 nx=0 f=1", padded to the column width
+        pub fn decode(&self, data: i16) -> String<PAGE_STR_WIDTH> {
+            let mut text: String<PAGE_STR_WIDTH> = String::new();
+            for field in self.fields.iter() {
+                let value = (data as u16 & bit_mask(field.hi, field.lo)) >> field.lo;
+                let mut part: String<PAGE_STR_WIDTH> = String::new();
+                write!(part, "{}={} ", field.name, value).unwrap();
+                if text.len() + part.len() > FIELD_TEXT_WIDTH + 1 {
+                    break;
+                }
+                text.push_str(&part).unwrap();
+            }
+            while text.len() < FIELD_TEXT_WIDTH {
+                text.push(' ').unwrap();
+            }
+            text.truncate(FIELD_TEXT_WIDTH);
+            text
+        }
+    }
+
+    impl Persist for FieldLayout {
+        fn store(&self, w: &mut impl Sink) {
+            w.put_u8(self.fields.len() as u8);
+            for field in self.fields.iter() {
+                w.put_str(&field.name);
+                w.put_u8(field.hi);
+                w.put_u8(field.lo);
+            }
+        }
+
+        fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError> {
+            self.fields.clear();
+            for _ in 0..r.get_u8()? {
+                let field = Field {
+                    name: r.get_str()?,
+                    hi: r.get_u8()?,
+                    lo: r.get_u8()?,
+                };
+                if field.hi > 15 || field.lo > field.hi {
+                    return Err(StorageError::Corrupt);
+                }
+                self.fields.push(field).map_err(|_| StorageError::Corrupt)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::vec::Vec;
+
+        fn parse(text: &str) -> Result<FieldLayout, &'static str> {
+            let definitions: Vec<String<PAGE_STR_WIDTH>> = text
+                .split_whitespace()
+                .map(|d| d.try_into().unwrap())
+                .collect();
+            FieldLayout::parse(&definitions)
+        }
+
+        fn err(text: &str) -> &'static str {
+            parse(text)
+                .err()
+                .unwrap_or_else(|| panic!("{} was accepted", text))
+        }
+
+        #[test]
+        fn parses_bits_and_ranges() {
+            let layout = parse("out:0-13 zr:14 ng:15").unwrap();
+            let out = layout.find("out").unwrap();
+            assert_eq!((out.hi, out.lo), (13, 0));
+            let ng = layout.find("ng").unwrap();
+            assert_eq!((ng.hi, ng.lo), (15, 15));
+            let swapped = parse("a:7-4").unwrap();
+            assert_eq!((swapped.fields[0].hi, swapped.fields[0].lo), (7, 4));
+        }
+
+        #[test]
+        fn rejects_bad_fields() {
+            assert_eq!(err("a:0-7 b:7"), "Err: fields overlap");
+            assert_eq!(err("a:16"), "Err: field bits must be 0-15");
+            assert_eq!(err("a:0-16"), "Err: field bits must be 0-15");
+            assert_eq!(err("a:x"), "Err: no valid field bits found");
+            assert_eq!(err("a"), "Err: field needs name:bit");
+            assert_eq!(err(":3"), "Err: no valid field name found");
+            assert_eq!(err("toolongname:3"), "Err: field name too long");
+            assert_eq!(err("a:1 a:2"), "Err: duplicate field name");
+        }
+
+        #[test]
+        fn empty_definition_clears() {
+            let layout = parse("").unwrap();
+            assert!(layout.is_empty());
+            assert_eq!(
+                layout.assign(0, &[]),
+                Err("Err: no fields defined for channel")
+            );
+        }
+
+        #[test]
+        fn decodes_and_assigns() {
+            let layout = parse("zx:15 nx:14 f:11 out:0-3").unwrap();
+            let text = layout.decode(0x880Au16 as i16);
+            assert_eq!(text.len(), FIELD_TEXT_WIDTH);
+            assert_eq!(text.trim_end(), "zx=1 nx=0 f=1 out=10");
+            let assignments: [String<PAGE_STR_WIDTH>; 2] =
+                ["nx=1".try_into().unwrap(), "out=0xF".try_into().unwrap()];
+            assert_eq!(layout.assign(0, &assignments), Ok(0x400F));
+            let too_big: [String<PAGE_STR_WIDTH>; 1] = ["f=2".try_into().unwrap()];
+            assert_eq!(
+                layout.assign(0, &too_big),
+                Err("Err: value does not fit into field")
+            );
+        }
+    }
+}
@@ -4,8 +4,8 @@ pub mod format_str {
     use heapless::String;
 
     const SCROLL_LINES: usize = 5;
-    pub const PAGE_LINES: usize = 18;
-    pub const PAGE_WIDTH: usize = 80;
+    pub const PAGE_LINES: usize = 26;
+    pub const PAGE_WIDTH: usize = 120;
     pub const PAGE_STR_WIDTH: usize = PAGE_WIDTH + 20;
     const NEW_INTERVAL: u64 = 5_000;
     pub struct ScrollText {
@@ -109,6 +109,13 @@ pub mod format_str {
             &self.print_text
         }
 
+        // show inverted until the next set
+        pub fn highlight(&mut self) {
+            self.is_new = false;
+            self.print_text = add_position(&invert(&self.text), self.x, self.y);
+            self.is_changed = true;
+        }
+
         pub fn get_cursor(&self) -> (u8, u8) {
             (self.x + self.text.len() as u8, self.y)
         }
@@ -162,7 +169,7 @@ pub mod format_str {
     pub fn invert(str: &String<PAGE_STR_WIDTH>) -> String<PAGE_STR_WIDTH> {
         let mut result = String::new();
         result.push_str(INVERTED_ON).unwrap();
-        result.push_str(&str).unwrap();
+        result.push_str(str).unwrap();
         result.push_str(INVERTED_OFF).unwrap();
         result
     }
@@ -180,7 +187,7 @@ pub mod format_str {
     pub fn bold(str: &String<PAGE_STR_WIDTH>) -> String<PAGE_STR_WIDTH> {
         let mut result = String::new();
         result.push_str(BOLD_ON).unwrap();
-        result.push_str(&str).unwrap();
+        result.push_str(str).unwrap();
         result.push_str(BOLD_OFF).unwrap();
         result
     }
@@ -220,15 +227,14 @@ pub mod format_str {
     impl DynamicFormatArg for String<PAGE_STR_WIDTH> {
         fn format(&self, f: &mut dyn core::fmt::Write, fmt: &str) -> core::fmt::Result {
             match fmt {
+                "{:>6}" => write!(f, "{:>6}", self),
                 _ => write!(f, "{}", self),
             }
         }
     }
     impl DynamicFormatArg for &str {
-        fn format(&self, f: &mut dyn core::fmt::Write, fmt: &str) -> core::fmt::Result {
-            match fmt {
-                _ => write!(f, "{}", self),
-            }
+        fn format(&self, f: &mut dyn core::fmt::Write, _fmt: &str) -> core::fmt::Result {
+            write!(f, "{}", self)
         }
     }
 }
@@ -0,0 +1,305 @@
+pub mod frequency {
+    use core::fmt::{Display, Formatter, Result, Write};
+
+    // Frequency in mHz, parsed from the terminal
+    //
+    //     20kHz  1.5MHz  0.5Hz  250mHz  10 (Hz)  period 3ms  period 2.5us
+    //
+    // A period is converted into the frequency it repeats at.
+
+    #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+    pub struct Frequency(u64);
+
+    const MILLIHERTZ_NANOS: u64 = 1_000_000_000_000; // mHz * ns per period
+
+    impl Frequency {
+        pub const fn from_millihertz(millihertz: u64) -> Self {
+            Frequency(millihertz)
+        }
+
+        pub const fn from_hertz(hertz: u64) -> Self {
+            Frequency(hertz * 1000)
+        }
+
+        pub fn millihertz(&self) -> u64 {
+            self.0
+        }
+
+        // "20kHz" or "period 3ms", a number without unit is in Hz
+        pub fn parse(text: &str) -> core::result::Result<Self, &'static str> {
+            let text = text.trim();
+            let millihertz = if let Some(period) = text.strip_prefix("period") {
+                let period = period.trim_start();
+                let (number, exp) = if let Some(n) = period.strip_suffix("ms") {
+                    (n, 6)
+                } else if let Some(n) = period.strip_suffix("us") {
+                    (n, 3)
+                } else if let Some(n) = period.strip_suffix("ns") {
+                    (n, 0)
+                } else if let Some(n) = period.strip_suffix('s') {
+                    (n, 9)
+                } else {
+                    return Err("Err: period needs a unit s/ms/us/ns");
+                };
+                let nanos = parse_scaled(number, exp).ok_or("Err: no valid period found")?;
+                if nanos == 0 {
+                    return Err("Err: period must be above 0");
+                }
+                (MILLIHERTZ_NANOS + nanos / 2) / nanos
+            } else {
+                let (number, exp) = if let Some(n) = text.strip_suffix("MHz") {
+                    (n, 9)
+                } else if let Some(n) = text.strip_suffix("mHz") {
+                    (n, 0)
+                } else if let Some(n) = text
+                    .strip_suffix("kHz")
+                    .or_else(|| text.strip_suffix("khz"))
+                {
+                    (n, 6)
+                } else if let Some(n) = text.strip_suffix("Hz").or_else(|| text.strip_suffix("hz"))
+                {
+                    (n, 3)
+                } else {
+                    (text, 3)
+                };
+                parse_scaled(number, exp).ok_or("Err: no valid frequency found")?
+            };
+            if millihertz == 0 {
+                return Err("Err: frequency must be above 0");
+            }
+            Ok(Frequency(millihertz))
+        }
+
+        // error unless min <= self <= max
+        pub fn check_range(
+            &self,
+            min: Frequency,
+            max: Frequency,
+        ) -> core::result::Result<(), &'static str> {
+            if *self < min {
+                Err("Err: frequency too low")
+            } else if *self > max {
+                Err("Err: frequency too high")
+            } else {
+                Ok(())
+            }
+        }
+
+        // length of a period in ticks of a timer running at tick_hz
+        pub fn period_ticks(&self, tick_hz: u64) -> u64 {
+            (tick_hz * 1000 + self.0 / 2) / self.0
+        }
+
+        // frequency of a period of ticks of a timer running at tick_hz
+        pub fn from_period_ticks(ticks: u64, tick_hz: u64) -> Self {
+            Frequency((tick_hz * 1000 + ticks / 2) / ticks.max(1))
+        }
+
+        // 3 significant digits and a prefix without unit for the dashboard,
+        // "250m", "1.50", "20.0k"
+        pub fn write_short(&self, w: &mut impl Write) -> Result {
+            let (unit, prefix) = prefix(self.0);
+            let decimals = match self.0 / unit {
+                0..=9 => 2,
+                10..=99 => 1,
+                _ => 0,
+            };
+            let scale = 10u64.pow(decimals);
+            let value = (self.0 * scale + unit / 2) / unit;
+            if decimals == 0 {
+                write!(w, "{}{}", value, prefix)
+            } else {
+                write!(
+                    w,
+                    "{}.{:0width$}{}",
+                    value / scale,
+                    value % scale,
+                    prefix,
+                    width = decimals as usize
+                )
+            }
+        }
+    }
+
+    // "12.5 kHz", up to 3 decimals
+    impl Display for Frequency {
+        fn fmt(&self, f: &mut Formatter) -> Result {
+            let (unit, prefix) = prefix(self.0);
+            write!(f, "{}", self.0 / unit)?;
+            let mut fraction = (self.0 % unit) * 1000 / unit;
+            if fraction > 0 {
+                let mut digits = 3;
+                while fraction.is_multiple_of(10) {
+                    fraction /= 10;
+                    digits -= 1;
+                }
+                write!(f, ".{:0width$}", fraction, width = digits)?;
+            }
+            write!(f, " {}Hz", prefix)
+        }
+    }
+
+    // mHz per unit and the SI prefix for a frequency in mHz
+    fn prefix(millihertz: u64) -> (u64, &'static str) {
+        match millihertz {
+            0..=999 => (1, "m"),
+            1_000..=999_999 => (1_000, ""),
+            1_000_000..=999_999_999 => (1_000_000, "k"),
+            _ => (1_000_000_000, "M"),
+        }
+    }
+
+    // decimal number times 10^exp, "2.5" with exp 3 is 2500
+    fn parse_scaled(text: &str, exp: u32) -> Option<u64> {
+        let text = text.trim();
+        let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if !all_digits(integer) || !all_digits(fraction) {
+            return None;
+        }
+        let mut value = match integer {
+            "" => 0,
+            _ => integer.parse::<u64>().ok()?,
+        };
+        value = value.checked_mul(10u64.pow(exp))?;
+        // digits beyond the resolution are dropped
+        for (i, c) in fraction.chars().take(exp as usize).enumerate() {
+            value += (c as u64 - '0' as u64) * 10u64.pow(exp - 1 - i as u32);
+        }
+        Some(value)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::string::String;
+
+        fn millihertz(text: &str) -> u64 {
+            Frequency::parse(text)
+                .unwrap_or_else(|e| panic!("{}: {}", text, e))
+                .millihertz()
+        }
+
+        fn short(freq: Frequency) -> String {
+            let mut text = String::new();
+            freq.write_short(&mut text).unwrap();
+            text
+        }
+
+        #[test]
+        fn units_scale_to_millihertz() {
+            assert_eq!(millihertz("20kHz"), 20_000_000);
+            assert_eq!(millihertz("20khz"), 20_000_000);
+            assert_eq!(millihertz("1.5MHz"), 1_500_000_000);
+            assert_eq!(millihertz("0.5Hz"), 500);
+            assert_eq!(millihertz(".5Hz"), 500);
+            assert_eq!(millihertz("250mHz"), 250);
+            assert_eq!(millihertz("10"), 10_000);
+            assert_eq!(millihertz("1.2345Hz"), 1_234); // below 1 mHz is dropped
+        }
+
+        #[test]
+        fn periods_round_to_millihertz() {
+            assert_eq!(millihertz("period 3ms"), 333_333);
+            assert_eq!(millihertz("period 2.5us"), 400_000_000);
+            assert_eq!(millihertz("period 1s"), 1_000);
+            assert_eq!(millihertz("period 3ns"), 333_333_333_333);
+        }
+
+        #[test]
+        fn malformed_input() {
+            for text in ["", "abc", "kHz", "1.2.3Hz", "-5Hz", "1,5kHz", "5 GHz"] {
+                assert_eq!(
+                    Frequency::parse(text),
+                    Err("Err: no valid frequency found"),
+                    "{}",
+                    text
+                );
+            }
+            assert_eq!(
+                Frequency::parse("period 3"),
+                Err("Err: period needs a unit s/ms/us/ns")
+            );
+            assert_eq!(
+                Frequency::parse("period xms"),
+                Err("Err: no valid period found")
+            );
+            assert_eq!(
+                Frequency::parse("period 0ms"),
+                Err("Err: period must be above 0")
+            );
+            assert_eq!(
+                Frequency::parse("0Hz"),
+                Err("Err: frequency must be above 0")
+            );
+            assert_eq!(
+                Frequency::parse("0.0001Hz"),
+                Err("Err: frequency must be above 0")
+            );
+            assert_eq!(
+                Frequency::parse("99999999999MHz"),
+                Err("Err: no valid frequency found")
+            );
+        }
+
+        #[test]
+        fn range_check() {
+            let (min, max) = (
+                Frequency::from_millihertz(100),
+                Frequency::from_hertz(1_000),
+            );
+            assert_eq!(
+                Frequency::from_millihertz(100).check_range(min, max),
+                Ok(())
+            );
+            assert_eq!(Frequency::from_hertz(1_000).check_range(min, max), Ok(()));
+            assert_eq!(
+                Frequency::from_millihertz(99).check_range(min, max),
+                Err("Err: frequency too low")
+            );
+            assert_eq!(
+                Frequency::from_millihertz(1_000_001).check_range(min, max),
+                Err("Err: frequency too high")
+            );
+        }
+
+        #[test]
+        fn period_ticks_round_trip() {
+            let tick_hz = 1_000_000;
+            let exact = Frequency::parse("20kHz").unwrap();
+            assert_eq!(exact.period_ticks(tick_hz), 50);
+            assert_eq!(Frequency::from_period_ticks(50, tick_hz), exact);
+            // 3 kHz is 333.3 ticks, the achieved frequency is reported
+            let rounded = Frequency::parse("3kHz").unwrap();
+            let ticks = rounded.period_ticks(tick_hz);
+            assert_eq!(ticks, 333);
+            let achieved = Frequency::from_period_ticks(ticks, tick_hz);
+            assert_eq!(achieved.millihertz(), 3_003_003);
+            assert_eq!(achieved.period_ticks(tick_hz), ticks);
+            assert_eq!(
+                Frequency::from_period_ticks(0, tick_hz).millihertz(),
+                1_000_000_000
+            );
+        }
+
+        #[test]
+        fn short_and_long_text() {
+            assert_eq!(short(Frequency::from_millihertz(250)), "250m");
+            assert_eq!(short(Frequency::from_millihertz(1_500)), "1.50");
+            assert_eq!(short(Frequency::from_hertz(20_000)), "20.0k");
+            assert_eq!(short(Frequency::from_hertz(1_500_000)), "1.50M");
+            assert_eq!(short(Frequency::from_millihertz(3_003_003)), "3.00k");
+            assert_eq!(
+                std::format!("{}", Frequency::from_hertz(12_500)),
+                "12.5 kHz"
+            );
+            assert_eq!(
+                std::format!("{}", Frequency::from_millihertz(500)),
+                "500 mHz"
+            );
+        }
+    }
+}
@@ -0,0 +1,14 @@
+// Hardware independent modules, built for the host to run their tests:
+//
+//     cargo test-host
+#![cfg_attr(not(test), no_std)]
+#![allow(clippy::module_inception)] // every module wraps itself like in main.rs
+
+pub mod bits;
+pub mod breakpoint;
+pub mod clock_calc;
+pub mod field_layout;
+pub mod format_str;
+pub mod frequency;
+pub mod output_channel;
+pub mod storage;
@@ -1,54 +1,97 @@
 #![no_std]
 #![no_main]
 
+mod bits;
+mod breakpoint;
 mod button;
 mod clock;
+mod clock_calc;
+mod field_layout;
 mod format_str;
+mod frequency;
 mod input_channel;
 mod output_channel;
+mod pio_clock;
+mod preset;
 mod program;
+mod rp_flash;
+mod settings;
+mod storage;
+mod sync;
 mod text_input;
+mod trigger;
+mod wavedrom;
+mod waveform;
 use core::array::from_fn;
 
 //mod string;
 
 use format_str::format_str::{DataText, ScrollText, StaticPageText};
+use frequency::frequency::Frequency;
 
 use core::{fmt::Write, str::FromStr};
 use embedded_hal::digital::StatefulOutputPin as _;
 use heapless::{String, Vec};
 use panic_halt as _;
 use rp2040_hal::{
-    clocks::init_clocks_and_plls, gpio::PinState, pac, sio::Sio, watchdog::Watchdog, Timer,
+    clocks::init_clocks_and_plls, clocks::Clock as _, gpio::PinState, pac, sio::Sio,
+    watchdog::Watchdog, Timer,
 };
 use usb_device::{class_prelude::*, prelude::*};
 use usbd_serial::{SerialPort, USB_CLASS_CDC};
 
+use bits::bits::{apply_bit_op, parse_bit_op, parse_input_bits, parse_value, BitOp};
+use breakpoint::breakpoint::{Breakpoints, Condition};
 use button::button::Button;
-use clock::clock::{Clock, ClockMode};
+use clock::clock::{Clock, ClockMode, Phase};
+use field_layout::field_layout::FieldLayout;
 use input_channel::input_channel::InputChannel;
-use output_channel::output_channel::OutputChannel;
-use program::program::{Program, ProgramControl, ProgramMode};
+use output_channel::output_channel::{OutputChannel, Pace, QUEUE_LENGTH};
+use pio_clock::pio_clock::PioClocks;
+use preset::preset::{Preset, PresetStore, PRESET_NAME_WIDTH};
+use program::program::{
+    program_name, ActionKind, Program, ProgramControl, ProgramMode, RunUnit, StepAction,
+    StepTiming, BURST_ERROR, MAX_BURST, MAX_PROGRAMS,
+};
+use rp_flash::rp_flash::RpFlash;
+use settings::settings::Settings;
+use storage::storage::{SettingsStore, StorageError};
+use sync::sync::{Sync, SyncMode};
 use text_input::text_input::{TextInput, TextInputState};
+use trigger::trigger::{Edge, Trigger, TriggerAction};
+use wavedrom::wavedrom::{ImportState, WaveImport};
+use waveform::waveform::{write_waveform_row, WAVE_ROWS};
 //use string::string::String;
 
 const CRYSTAL_FREQ: u32 = 12_000_000; // System frequency in Hz
 const TICKS_SECOND: u32 = 1_000_000; // USB Vendor ID
 
 const PAGE_STR_WIDTH: usize = format_str::format_str::PAGE_STR_WIDTH;
+const PAGE_WIDTH: usize = format_str::format_str::PAGE_WIDTH;
+const WAVE_LINE: u8 = 10; // first line of the program waveform
+const WAVE_INTERVAL: u64 = 100_000; // redraw the waveform at most every 100ms
+const DUMP_LINE: usize = format_str::format_str::PAGE_LINES + 2; // exports below the dashboard
+const WRITE_STALL_TICKS: u64 = 50_000; // give up a write if the host reads nothing for 50ms
+
+// settings region at the end of the flash, see memory.x
+const SETTINGS_OFFSET: u32 = 0x1F_0000;
+const SETTINGS_SIZE: u32 = 0x1_0000;
+const SETTINGS_SLOTS: u32 = 4;
 
 #[link_section = ".boot2"]
 #[used]
 pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_GENERIC_03H;
 
 static OUTPUT_CHANNELS: usize = 4;
+const MAX_TOKENS: usize = 20;
 
-fn tokenize(input: String<PAGE_STR_WIDTH>) -> Vec<String<PAGE_STR_WIDTH>, 4> {
-    let mut tokens: Vec<String<PAGE_STR_WIDTH>, 4> = Vec::new();
-    for s in input.trim().split(" ") {
-        tokens.push(String::from_str(s).unwrap()).unwrap();
+// split input into tokens, None if there are more than MAX_TOKENS
+fn tokenize(input: String<PAGE_STR_WIDTH>) -> Option<Vec<String<PAGE_STR_WIDTH>, MAX_TOKENS>> {
+    let mut tokens: Vec<String<PAGE_STR_WIDTH>, MAX_TOKENS> = Vec::new();
+    for s in input.split_whitespace() {
+        tokens.push(String::from_str(s).unwrap()).ok()?;
     }
-    tokens
+    Some(tokens)
 }
 
 fn get_channels_from_text(text: &String<PAGE_STR_WIDTH>) -> [bool; OUTPUT_CHANNELS] {
@@ -63,6 +106,118 @@ fn get_channels_from_text(text: &String<PAGE_STR_WIDTH>) -> [bool; OUTPUT_CHANNE
     channels
 }
 
+// join tokens with spaces, e.g. a preset name like "ALU test"
+fn join_tokens<const N: usize>(tokens: &[String<PAGE_STR_WIDTH>]) -> Option<String<N>> {
+    let mut text: String<N> = String::new();
+    for (i, t) in tokens.iter().enumerate() {
+        if i > 0 {
+            text.push(' ').ok()?;
+        }
+        text.push_str(t).ok()?;
+    }
+    Some(text)
+}
+
+// frequency for the dashboard, "20.0k"
+fn freq_text(freq: &Frequency) -> String<PAGE_STR_WIDTH> {
+    let mut text = String::new();
+    freq.write_short(&mut text).unwrap();
+    text
+}
+
+// writes everything to the serial port, polls USB while the buffer is full,
+// fails if the host stops reading so the main loop keeps running
+struct SerialWriter<'w, 'b, B: UsbBus> {
+    usb_dev: &'w mut UsbDevice<'b, B>,
+    serial: &'w mut SerialPort<'b, B>,
+    timer: Timer,
+}
+
+impl<B: UsbBus> Write for SerialWriter<'_, '_, B> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut buf = s.as_bytes();
+        let mut progress_at = self.timer.get_counter().ticks();
+        while !buf.is_empty() {
+            match self.serial.write(buf) {
+                Ok(n) if n > 0 => {
+                    buf = &buf[n..];
+                    progress_at = self.timer.get_counter().ticks();
+                }
+                Ok(_) | Err(UsbError::WouldBlock) => {
+                    if self.timer.get_counter().ticks() - progress_at > WRITE_STALL_TICKS {
+                        return Err(core::fmt::Error);
+                    }
+                }
+                Err(_) => return Err(core::fmt::Error),
+            }
+            self.usb_dev.poll(&mut [&mut *self.serial]);
+        }
+        Ok(())
+    }
+}
+
+// program mode as shown in the dashboard
+fn mode_text(mode: &ProgramMode) -> &'static str {
+    match mode {
+        ProgramMode::Manual | ProgramMode::OneShot => "    ",
+        ProgramMode::Auto => "AUTO",
+        ProgramMode::Run => "RUN ",
+    }
+}
+
+fn clock_mode_text(mode: &ClockMode) -> &'static str {
+    match mode {
+        ClockMode::Manual => "    ",
+        ClockMode::Auto => "AUTO",
+        ClockMode::Burst => "BRST",
+    }
+}
+
+// pulse counter of a burst, padded to overwrite longer texts
+fn burst_text(done: u32, total: u32) -> String<PAGE_STR_WIDTH> {
+    let mut text = String::new();
+    write!(text, "{}/{}", done, total).unwrap();
+    while text.len() < 10 {
+        text.push(' ').unwrap();
+    }
+    text
+}
+
+// rising edges of a clock, same width as the burst counter
+fn count_text(cycles: u64) -> String<PAGE_STR_WIDTH> {
+    let mut text = String::new();
+    write!(text, "{:<10}", cycles).unwrap();
+    text
+}
+
+// number of queued values, blank if there are none
+fn queue_text(len: usize) -> String<PAGE_STR_WIDTH> {
+    let mut text = String::new();
+    match len {
+        0 => text.push(' ').unwrap(),
+        _ => write!(text, "{}", len).unwrap(),
+    }
+    text
+}
+
+struct ChannelDataText {
+    data_text: [DataText; 5],
+}
+
+impl ChannelDataText {
+    // show value in all number formats and decoded by the field layout
+    fn show(&mut self, data: i16, layout: &FieldLayout, now: u64) {
+        for text in self.data_text[0..3].iter_mut() {
+            text.set(&data, now);
+        }
+        self.data_text[4].set(&layout.decode(data), now);
+    }
+
+    fn show_reverse(&mut self, reverse: bool, now: u64) {
+        self.data_text[3].set(&if reverse { "R" } else { " " }, now);
+    }
+}
+
 #[rp2040_hal::entry]
 fn main() -> ! {
     /////////////////////////////////////
@@ -203,6 +358,24 @@ fn main() -> ! {
         .into_push_pull_output_in_state(PinState::Low)
         .into_dyn_pin();
 
+    // Program Phase Output Pins (signals 3 - 6)
+    let pin_2 = pins
+        .gpio2
+        .into_push_pull_output_in_state(PinState::Low)
+        .into_dyn_pin();
+    let pin_3 = pins
+        .gpio3
+        .into_push_pull_output_in_state(PinState::Low)
+        .into_dyn_pin();
+    let pin_4 = pins
+        .gpio4
+        .into_push_pull_output_in_state(PinState::Low)
+        .into_dyn_pin();
+    let pin_5 = pins
+        .gpio5
+        .into_push_pull_output_in_state(PinState::Low)
+        .into_dyn_pin();
+
     // Input Channel Pins
     let pin_6 = pins
         .gpio6
@@ -247,8 +420,14 @@ fn main() -> ! {
             .unwrap();
     }
 
-    // setup clocks
+    // setup clocks, the PIO state machines can take over their pins
     let clocks = [Clock::new(pin_10, now), Clock::new(pin_11, now)];
+    let pio_clocks = PioClocks::new(
+        pac.PIO0,
+        &mut pac.RESETS,
+        [10, 11],
+        sys_clocks.system_clock.freq().to_Hz(),
+    );
 
     // setup input channel
     let mut input_channel = InputChannel::new(pin_6, pin_8, pin_9, pin_7, now);
@@ -256,6 +435,19 @@ fn main() -> ! {
     // setup buttons
     let buttons: [Button; 2] = [Button::new(pin_12), Button::new(pin_13)];
 
+    // setup external trigger input
+    let mut trigger = Trigger::new(Button::new(
+        pins.gpio22.into_pull_down_input().into_dyn_pin(),
+    ));
+
+    // setup sync between boards, GPIO27 goes to GPIO26 of the follower
+    let sync = Sync::new(
+        pins.gpio27
+            .into_push_pull_output_in_state(PinState::Low)
+            .into_dyn_pin(),
+        pins.gpio26.into_pull_down_input().into_dyn_pin(),
+    );
+
     /////////////////////////////////////
     // Setup Watchdog LED
     /////////////////////////////////////
@@ -268,33 +460,50 @@ fn main() -> ! {
     /////////////////////////////////////
 
     // Program 0 is special, will be caught by the main loop
-    let mut prog = ProgramControl::new(TICKS_SECOND, clocks, buttons);
+    let mut prog = ProgramControl::new(
+        TICKS_SECOND,
+        clocks,
+        pio_clocks,
+        [pin_2, pin_3, pin_4, pin_5],
+        buttons,
+        sync,
+    );
 
-    prog.add_program(Program::new(
-        String::from_str("Sync      ").unwrap(),
-        2,
-        [0b01, 0b01],
-    ));
-    prog.add_program(Program::new(
-        String::from_str("Opp Sync  ").unwrap(),
-        2,
-        [0b10, 0b01],
-    ));
-    prog.add_program(Program::new(
-        String::from_str("Inner     ").unwrap(),
-        4,
-        [0b0111, 0b0010],
-    ));
-    prog.add_program(Program::new(
-        String::from_str("Overlap   ").unwrap(),
-        4,
-        [0b0110, 0b0011],
-    ));
-    prog.add_program(Program::new(
-        String::from_str("Sequential").unwrap(),
-        4,
-        [0b0100, 0b0001],
-    ));
+    for (name, sequence) in [
+        ("Sync      ", "01/01"),
+        ("Opp Sync  ", "10/01"),
+        ("Inner     ", "0111/0010"),
+        ("Overlap   ", "0110/0011"),
+        ("Sequential", "0100/0001"),
+    ] {
+        prog.add_program(Program::from_text(String::from_str(name).unwrap(), sequence).unwrap());
+    }
+
+    /////////////////////////////////////
+    // Setup Settings
+    /////////////////////////////////////
+
+    // setup field layouts (named bit fields per channel)
+    let mut output_field_layouts: [FieldLayout; OUTPUT_CHANNELS] = from_fn(|_| FieldLayout::new());
+    let mut input_field_layout = FieldLayout::new();
+
+    // setup presets
+    let mut presets = PresetStore::new();
+
+    // load stored settings, defaults stay if there are none
+    let mut settings_store = SettingsStore::new(
+        RpFlash::new(),
+        SETTINGS_OFFSET,
+        SETTINGS_SIZE,
+        SETTINGS_SLOTS,
+    );
+    let settings_loaded = settings_store.load(&mut Settings {
+        prog: &mut prog,
+        output_channels: &mut output_channels,
+        output_field_layouts: &mut output_field_layouts,
+        input_field_layout: &mut input_field_layout,
+        presets: &mut presets,
+    });
 
     /////////////////////////////////////
     // Setup Output Text
@@ -305,10 +514,7 @@ fn main() -> ! {
     let channel_format_hex: String<PAGE_STR_WIDTH> = String::from_str("{:#06X}").unwrap();
     let channel_format_bin: String<PAGE_STR_WIDTH> = String::from_str("{:#018b}").unwrap();
     let channel_format_inverted: String<PAGE_STR_WIDTH> = String::from_str("{}").unwrap();
-
-    struct ChannelDataText {
-        data_text: [DataText; 4],
-    }
+    let channel_format_fields: String<PAGE_STR_WIDTH> = String::from_str("{}").unwrap();
 
     // setup output channel text
     let mut output_channel_data_text: [ChannelDataText; OUTPUT_CHANNELS] = [
@@ -318,6 +524,7 @@ fn main() -> ! {
                 DataText::new(channel_format_hex.clone(), 17, 3, true),
                 DataText::new(channel_format_bin.clone(), 25, 3, true),
                 DataText::new(channel_format_inverted.clone(), 7, 3, true),
+                DataText::new(channel_format_fields.clone(), 80, 3, false),
             ],
         },
         ChannelDataText {
@@ -326,6 +533,7 @@ fn main() -> ! {
                 DataText::new(channel_format_hex.clone(), 17, 4, true),
                 DataText::new(channel_format_bin.clone(), 25, 4, true),
                 DataText::new(channel_format_inverted.clone(), 7, 4, true),
+                DataText::new(channel_format_fields.clone(), 80, 4, false),
             ],
         },
         ChannelDataText {
@@ -334,6 +542,7 @@ fn main() -> ! {
                 DataText::new(channel_format_hex.clone(), 17, 5, true),
                 DataText::new(channel_format_bin.clone(), 25, 5, true),
                 DataText::new(channel_format_inverted.clone(), 7, 5, true),
+                DataText::new(channel_format_fields.clone(), 80, 5, false),
             ],
         },
         ChannelDataText {
@@ -342,6 +551,7 @@ fn main() -> ! {
                 DataText::new(channel_format_hex.clone(), 17, 6, true),
                 DataText::new(channel_format_bin.clone(), 25, 6, true),
                 DataText::new(channel_format_inverted.clone(), 7, 6, true),
+                DataText::new(channel_format_fields.clone(), 80, 6, false),
             ],
         },
     ];
@@ -353,28 +563,56 @@ fn main() -> ! {
             DataText::new(channel_format_hex.clone(), 17, 8, true),
             DataText::new(channel_format_bin.clone(), 25, 8, true),
             DataText::new(channel_format_inverted.clone(), 7, 8, true),
+            DataText::new(channel_format_fields.clone(), 80, 8, false),
         ],
     };
 
+    // setup output queue text, queued values of the output channels
+    let mut output_queue_data_text: [DataText; OUTPUT_CHANNELS] =
+        from_fn(|i| DataText::new(String::from_str("{}").unwrap(), 44, 3 + i as u8, false));
+    let mut queue_cycles = [0u64; 2]; // clock cycles at the last queue update
+
+    // halt conditions on the input channel
+    let mut breakpoints = Breakpoints::new();
+    let mut gate_shown = false; // modes show GATE while paused
+
     // setup text input (command line)
     let mut input_buffer: TextInput = TextInput::new();
+    let mut wave_import = WaveImport::new();
+    let mut import_name: Option<String<PAGE_STR_WIDTH>> = None; // set while pasting
+    let mut wave_shown: Option<(usize, u16)> = None; // program and state on screen
+    let mut wave_drawing: Option<((usize, u16), u8)> = None; // state being drawn, next row
+    let mut wave_next = 0u64;
     let input_format = String::from_str("{}                ").unwrap();
-    let mut input_data_text = DataText::new(input_format, 11, 17, false);
+    let mut input_data_text = DataText::new(input_format, 11, 25, false);
 
     // setup scroll text
-    let mut scroll_text: ScrollText = ScrollText::new(1, 11);
+    let mut scroll_text: ScrollText = ScrollText::new(1, 19);
+    scroll_text.add_line(match settings_loaded {
+        Ok(()) => "Settings loaded from flash",
+        Err(StorageError::NoData) => "No stored settings, using defaults",
+        Err(_) => "Err: stored settings invalid, using defaults",
+    });
 
     // setup static text
     let screen_str = [
-        "_____________________________________________________________________________",
+        "_____________________________________________________________________________________________________________________",
+        "                                           Q                                 | Fields                                ",
+        " OUT 1  -12345  0xFFFF  0b0000111100001111  |                     Mode    Hz |                                       ",
+        " OUT 2  -12345  0xFFFF  0b0000111100001111  |                                |                                       ",
+        " OUT 3  -12345  0xFFFF  0b0000111100001111  |  PROG 0 NAME______  AUTO    10 |                                       ",
+        " OUT 4  -12345  0xFFFF  0b0000111100001111  |                                |                                       ",
+        "                                            |  CLOCK 1            AUTO    20 |                                       ",
+        " IN     -12345  0xFFFF  0b0000111100001111  |  CLOCK 2            AUTO    30 |                                       ",
+        "_____________________________________________________________________________________________________________________",
+        "                                                                             ",
+        "                                                                             ",
+        "                                                                             ",
         "                                                                             ",
-        " OUT 1  -12345  0xFFFF  0b0000111100001111  |                     Mode  Freq ",
-        " OUT 2  -12345  0xFFFF  0b0000111100001111  |                                ",
-        " OUT 3  -12345  0xFFFF  0b0000111100001111  |  PROG 0 NAME______  AUTO    10 ",
-        " OUT 4  -12345  0xFFFF  0b0000111100001111  |                                ",
-        "                                            |  CLOCK 1    __XX__  AUTO    20 ",
-        " IN     -12345  0xFFFF  0b0000111100001111  |  CLOCK 2    __XX__  AUTO    30 ",
-        "_____________________________________________________________________________",
+        "                                                                             ",
+        "                                                                             ",
+        "                                                                             ",
+        "_____________________________________________________________________________________________________________________",
         "                                                                             ",
         "                                                                             ",
         "                                                                             ",
@@ -399,12 +637,29 @@ fn main() -> ! {
     let mut prog_number_data_text = DataText::new(String::from_str("{}").unwrap(), 53, 5, false);
     let mut prog_name_data_text = DataText::new(String::from_str("{}").unwrap(), 55, 5, false);
     let mut prog_mode_data_text = DataText::new(String::from_str("{}").unwrap(), 67, 5, false);
-    let mut prog_freq_data_text = DataText::new(String::from_str("{:>4}").unwrap(), 73, 5, false);
+    let mut prog_freq_data_text = DataText::new(String::from_str("{:>6}").unwrap(), 71, 5, false);
 
     prog_number_data_text.set(&(prog.get_current_program() as i16), now);
     prog_name_data_text.set(prog.get_current_program_name(), now);
     prog_mode_data_text.set(&"    ", now);
-    prog_freq_data_text.set(&(prog.prog_freq as i16), now);
+    prog_freq_data_text.set(&freq_text(&prog.prog_freq), now);
+
+    // current step of the program
+    let mut step_data_text = DataText::new(String::from_str("{}").unwrap(), 48, 4, false);
+
+    // progress of a counted run
+    let mut run_data_text = DataText::new(String::from_str("{}").unwrap(), 48, 6, false);
+    let mut run_next = 0u64;
+    let mut run_shown = false; // progress on screen, cleared when the run ends
+
+    // cycle counters of the clocks, pulse counters while a burst runs
+    let mut clock_count_data_text: [DataText; 2] = [
+        DataText::new(String::from_str("{}").unwrap(), 56, 7, false),
+        DataText::new(String::from_str("{}").unwrap(), 56, 8, false),
+    ];
+    let mut burst_shown = [false; 2]; // counter shown for a running burst
+    let mut count_shown: [Option<u64>; 2] = [None; 2];
+    let mut count_next = 0u64;
 
     // setup clock text
     let mut clock_mode_data_text: [DataText; 2] = [
@@ -412,12 +667,12 @@ fn main() -> ! {
         DataText::new(String::from_str("{}").unwrap(), 67, 8, false),
     ];
     let mut clock_freq_data_text: [DataText; 2] = [
-        DataText::new(String::from_str("{:>4}").unwrap(), 73, 7, false),
-        DataText::new(String::from_str("{:>4}").unwrap(), 73, 8, false),
+        DataText::new(String::from_str("{:>6}").unwrap(), 71, 7, false),
+        DataText::new(String::from_str("{:>6}").unwrap(), 71, 8, false),
     ];
     for i in 0..2 {
-        clock_mode_data_text[i].set(&"    ", now);
-        clock_freq_data_text[i].set(&(prog.clocks[i].freq as i16), now);
+        clock_mode_data_text[i].set(&clock_mode_text(&prog.clocks[i].mode), now);
+        clock_freq_data_text[i].set(&freq_text(&prog.clocks[i].freq), now);
     }
 
     /////////////////////////////////////
@@ -434,10 +689,8 @@ fn main() -> ! {
 
     // print initial values
     for i in 0..OUTPUT_CHANNELS {
-        output_channel_data_text[i].data_text[0].set(&0, now);
-        output_channel_data_text[i].data_text[1].set(&0, now);
-        output_channel_data_text[i].data_text[2].set(&0, now);
-        output_channel_data_text[i].data_text[3].set(&" ", now);
+        output_channel_data_text[i].show(output_channels[i].get(), &output_field_layouts[i], now);
+        output_channel_data_text[i].show_reverse(output_channels[i].is_reversed(), now);
     }
 
     let _ = serial.write(prog_number_data_text.get_text().as_bytes());
@@ -467,13 +720,184 @@ fn main() -> ! {
             blink_last = now;
         }
 
-        // handle output channels
-        for channel in output_channels.iter_mut() {
-            channel.update(now);
+        // handle output channels, queued values can wait for a clock edge,
+        // PIO clocks only give an estimate of their edges
+        let cycles = [
+            prog.clocks[0].get_cycles(now),
+            prog.clocks[1].get_cycles(now),
+        ];
+        let clock_rises = from_fn(|c| cycles[c] != queue_cycles[c]);
+        queue_cycles = cycles;
+        for (i, channel) in output_channels.iter_mut().enumerate() {
+            if channel.update(now, clock_rises) {
+                output_channel_data_text[i].show(channel.get(), &output_field_layouts[i], now);
+                output_queue_data_text[i].set(&queue_text(channel.queue_len()), now);
+            }
+        }
+
+        // handle external trigger
+        if let Some(action) = trigger.update(now) {
+            let result = match action {
+                TriggerAction::OneShot => prog.start_one_shot(),
+                TriggerAction::Burst { .. } if prog.get_current_program() != 0 => {
+                    Err("Err: trigger burst only in Prog 0")
+                }
+                TriggerAction::Burst { clock, pulses } => {
+                    prog.clock_start_burst(clock, pulses, now).map(|()| {
+                        burst_shown[clock] = true;
+                        count_next = 0;
+                        clock_mode_data_text[clock]
+                            .set(&clock_mode_text(&prog.clocks[clock].mode), now);
+                    })
+                }
+                TriggerAction::Output { channel, value } => {
+                    output_channels[channel].set(value);
+                    output_channel_data_text[channel].show(
+                        output_channels[channel].get(),
+                        &output_field_layouts[channel],
+                        now,
+                    );
+                    Ok(())
+                }
+            };
+            let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+            match result {
+                Ok(()) => write!(log_str, "Trigger: {}", action).unwrap(),
+                Err(e) => write!(log_str, "{} (trigger)", e).unwrap(),
+            }
+            scroll_text.add_line(&log_str);
         }
 
         // handle program control
-        prog.update(now);
+        if let Some(event) = prog.update(now, &mut output_channels, &input_channel) {
+            for i in 0..OUTPUT_CHANNELS {
+                if event.channels & 1 << i != 0 {
+                    output_channel_data_text[i].show(
+                        output_channels[i].get(),
+                        &output_field_layouts[i],
+                        now,
+                    );
+                }
+            }
+            // progress of a counted run, limited like the waveform
+            if prog.mode == ProgramMode::Run && now > run_next {
+                let (done, total, unit) = prog.get_run_progress();
+                let mut run_str: String<PAGE_STR_WIDTH> = String::new();
+                write!(
+                    run_str,
+                    "RUN {}/{} {}",
+                    done,
+                    total,
+                    match unit {
+                        RunUnit::Cycles => "cycles",
+                        RunUnit::Steps => "steps",
+                    }
+                )
+                .unwrap();
+                while run_str.len() < 30 {
+                    run_str.push(' ').unwrap(); // overwrite longer texts
+                }
+                run_data_text.set(&run_str, now);
+                run_shown = true;
+                run_next = now + WAVE_INTERVAL;
+            }
+            if event.run_finished {
+                let (done, _, unit) = prog.get_run_progress();
+                let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                write!(
+                    log_str,
+                    "Run finished after {} {}",
+                    done,
+                    match unit {
+                        RunUnit::Cycles => "cycles",
+                        RunUnit::Steps => "steps",
+                    }
+                )
+                .unwrap();
+                scroll_text.add_line(&log_str);
+                prog_mode_data_text.set(&mode_text(&prog.mode), now);
+            }
+            if event.one_shot {
+                let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                write!(
+                    log_str,
+                    "Step {} {}",
+                    event.step,
+                    prog.get_step_label().unwrap_or("")
+                )
+                .unwrap();
+                scroll_text.add_line(&log_str);
+            }
+            // a running program would flood the log, see 'prog log'
+            if let (Some(data), false) = (event.sample, prog.mode == ProgramMode::Auto) {
+                let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                write!(log_str, "Step {} read 0x{:04X}", event.step, data as u16).unwrap();
+                scroll_text.add_line(&log_str);
+            }
+        }
+
+        // a run ends when it finished, with 'a'/'p', a preset or a breakpoint
+        if run_shown && prog.mode != ProgramMode::Run {
+            let mut run_str: String<PAGE_STR_WIDTH> = String::new();
+            while run_str.len() < 30 {
+                run_str.push(' ').unwrap();
+            }
+            run_data_text.set(&run_str, now);
+            run_shown = false;
+        }
+
+        // paused by the gate bit
+        if prog.is_gated() != gate_shown {
+            gate_shown = prog.is_gated();
+            for (i, text) in clock_mode_data_text.iter_mut().enumerate() {
+                match prog.clocks[i].mode {
+                    ClockMode::Auto | ClockMode::Burst if gate_shown => text.set(&"GATE", now),
+                    _ => text.set(&clock_mode_text(&prog.clocks[i].mode), now),
+                }
+            }
+            if gate_shown && prog.mode != ProgramMode::Manual {
+                prog_mode_data_text.set(&"GATE", now);
+            } else {
+                prog_mode_data_text.set(&mode_text(&prog.mode), now);
+            }
+        }
+
+        // clock counters, limited like the waveform
+        if now > count_next {
+            for (i, text) in clock_count_data_text.iter_mut().enumerate() {
+                let bursting = matches!(prog.clocks[i].mode, ClockMode::Burst);
+                if !bursting && !burst_shown[i] {
+                    let cycles = prog.clocks[i].get_cycles(now);
+                    if count_shown[i] != Some(cycles) {
+                        text.set(&count_text(cycles), now);
+                        count_shown[i] = Some(cycles);
+                    }
+                    continue;
+                }
+                let (done, total) = prog.clocks[i].get_burst_progress();
+                text.set(&burst_text(done, total), now);
+                count_shown[i] = None;
+                if !bursting {
+                    // finished or stopped by 'c a' / 'c1/2 a'
+                    burst_shown[i] = false;
+                    clock_mode_data_text[i].set(&clock_mode_text(&prog.clocks[i].mode), now);
+                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                    if done < total {
+                        write!(
+                            log_str,
+                            "Clock {} burst stopped after {} pulses",
+                            i + 1,
+                            done
+                        )
+                        .unwrap();
+                    } else {
+                        write!(log_str, "Clock {} burst of {} pulses done", i + 1, total).unwrap();
+                    }
+                    scroll_text.add_line(&log_str);
+                }
+            }
+            count_next = now + WAVE_INTERVAL;
+        }
 
         // handle input channel
         input_channel.update(now);
@@ -484,7 +908,7 @@ fn main() -> ! {
 
         // print output channels
         for i in 0..OUTPUT_CHANNELS {
-            for format in 0..4 {
+            for format in 0..5 {
                 output_channel_data_text[i].data_text[format].update(now);
                 if output_channel_data_text[i].data_text[format].is_changed {
                     let _ = serial.write(
@@ -495,6 +919,9 @@ fn main() -> ! {
                     );
                 }
             }
+            if output_queue_data_text[i].is_changed {
+                let _ = serial.write(output_queue_data_text[i].get_text().as_str().as_bytes());
+            }
         }
 
         // print input channel
@@ -512,8 +939,112 @@ fn main() -> ! {
                 );
             }
         }
+        if input_channel.data_changed {
+            input_channel_data_text.data_text[4]
+                .set(&input_field_layout.decode(input_channel.data), now);
+        }
+        if input_channel_data_text.data_text[4].is_changed {
+            let _ = serial.write(
+                input_channel_data_text.data_text[4]
+                    .get_text()
+                    .as_str()
+                    .as_bytes(),
+            );
+        }
+        // breakpoints, the input row stays highlighted until it changes
+        if input_channel.data_changed {
+            if let Some(number) = breakpoints.check(input_channel.data) {
+                let stopped = prog.halt();
+                for (i, text) in clock_mode_data_text.iter_mut().enumerate() {
+                    text.set(&clock_mode_text(&prog.clocks[i].mode), now);
+                }
+                prog_mode_data_text.set(&mode_text(&prog.mode), now);
+                for text in input_channel_data_text.data_text[0..3].iter_mut() {
+                    text.highlight();
+                }
+                let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                write!(
+                    log_str,
+                    "Break {} ({}) at in 0x{:04X}, clock cycles {}/{}{}",
+                    number,
+                    breakpoints.iter().nth(number - 1).unwrap(),
+                    input_channel.data as u16,
+                    prog.clocks[0].get_cycles(now),
+                    prog.clocks[1].get_cycles(now),
+                    if stopped { ", halted" } else { "" }
+                )
+                .unwrap();
+                scroll_text.add_line(&log_str);
+            }
+        }
         input_channel.data_changed = false;
 
+        // print program and clocks
+        for text in [
+            &mut prog_number_data_text,
+            &mut prog_name_data_text,
+            &mut prog_mode_data_text,
+            &mut prog_freq_data_text,
+            &mut run_data_text,
+            &mut step_data_text,
+        ]
+        .into_iter()
+        .chain(clock_mode_data_text.iter_mut())
+        .chain(clock_freq_data_text.iter_mut())
+        .chain(clock_count_data_text.iter_mut())
+        {
+            if text.is_changed {
+                let _ = serial.write(text.get_text().as_str().as_bytes());
+            }
+        }
+
+        // print program waveform, the cursor follows the program state,
+        // one row per pass
+        let wave_state = (prog.get_current_program(), prog.get_state());
+        if wave_drawing.is_none() && wave_shown != Some(wave_state) && now > wave_next {
+            wave_drawing = Some((wave_state, 0));
+            let mut step_str: String<PAGE_STR_WIDTH> = String::new();
+            let program = match wave_state.0 {
+                0 => None,
+                n => prog.get_program(n),
+            };
+            if let Some(program) = program {
+                write!(
+                    step_str,
+                    "Step {}/{}",
+                    wave_state.1,
+                    program.get_sequence_length()
+                )
+                .unwrap();
+                if let Some(label) = program.get_label(wave_state.1) {
+                    write!(step_str, " {}", label).unwrap();
+                }
+            }
+            while step_str.len() < 30 {
+                step_str.push(' ').unwrap(); // overwrite longer texts
+            }
+            step_data_text.set(&step_str, now);
+        }
+        if let Some((state, row)) = wave_drawing {
+            let program = match state.0 {
+                0 => None,
+                n => prog.get_program(n),
+            };
+            let mut writer = SerialWriter {
+                usb_dev: &mut usb_dev,
+                serial: &mut serial,
+                timer,
+            };
+            let _ = write_waveform_row(&mut writer, program, state.1, 1, WAVE_LINE, row);
+            if row + 1 < WAVE_ROWS {
+                wave_drawing = Some((state, row + 1));
+            } else {
+                wave_drawing = None;
+                wave_shown = Some(state);
+                wave_next = now + WAVE_INTERVAL;
+            }
+        }
+
         // print scroll text
         if scroll_text.is_changed {
             for l in scroll_text.get_lines() {
@@ -549,6 +1080,46 @@ fn main() -> ! {
         // any input found?
         let mut input_str = String::new();
         if let Ok(count) = serial.read(&mut buf) {
+            // pasted WaveDrom description, may span several lines
+            if let Some(name) = &import_name {
+                for &c in buf[..count].iter() {
+                    if c == b'\x1B' {
+                        scroll_text.add_line("Import cancelled");
+                    } else {
+                        match wave_import.add_char(c) {
+                            ImportState::Collecting => continue,
+                            ImportState::Overflow => scroll_text.add_line("Err: import too long"),
+                            ImportState::Done => {
+                                match wavedrom::wavedrom::parse(wave_import.get_text()) {
+                                    Ok((signals, steps)) => {
+                                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                        write!(
+                                            log_str,
+                                            "Program {} '{}' imported ({} signals, {} steps)",
+                                            prog.number_of_programs(),
+                                            name.trim_end(),
+                                            signals,
+                                            steps.len()
+                                        )
+                                        .unwrap();
+                                        prog.add_program(Program::new(
+                                            name.clone(),
+                                            signals,
+                                            steps,
+                                        ));
+                                        scroll_text.add_line(&log_str);
+                                    }
+                                    Err(e) => scroll_text.add_line(e),
+                                }
+                            }
+                        }
+                    }
+                    wave_import.clear();
+                    import_name = None;
+                    break;
+                }
+                continue;
+            }
             let mut complete = false;
             for i in 0..count {
                 match input_buffer.add_char(buf[i]) {
@@ -569,7 +1140,15 @@ fn main() -> ! {
                 }
             }
             if complete {
-                let mut tokens = tokenize(input_str); // split input into tokens
+                // commands may change the program
+                wave_shown = None;
+                wave_drawing = None;
+
+                // split input into tokens
+                let Some(mut tokens) = tokenize(input_str) else {
+                    scroll_text.add_line("Err: too many tokens");
+                    continue;
+                };
                 let num_tokens = tokens.len();
                 // no tokens?
                 if num_tokens == 0 {
@@ -581,7 +1160,7 @@ fn main() -> ! {
                 match tokens[0].as_str() {
                     // for all channels
                     "z" | "0" | "r" => {
-                        if num_tokens == 4 {
+                        if num_tokens == MAX_TOKENS {
                             let mut log_str: String<PAGE_STR_WIDTH> =
                                 String::from_str("Too many tokens for '").unwrap();
                             write!(log_str, "{}", tokens[0]).unwrap();
@@ -595,6 +1174,86 @@ fn main() -> ! {
                     _ => {}
                 }
                 match tokens[0].as_str() {
+                    "step" | "back" | "goto" | "home" => {
+                        // step [n] / back [n] / goto n / home
+                        if prog.get_current_program() == 0 {
+                            scroll_text.add_line("Err: select a program with 'p' first");
+                            continue;
+                        }
+                        if prog.mode != ProgramMode::Manual {
+                            scroll_text.add_line("Err: stepping needs manual mode, stop with 'a'");
+                            continue;
+                        }
+                        let count = match (tokens[0].as_str(), tokens.get(1)) {
+                            ("home", None) => Some(0),
+                            ("goto", Some(t)) if num_tokens == 2 => t.parse::<u16>().ok(),
+                            ("step" | "back", Some(t)) if num_tokens == 2 => {
+                                t.parse::<u16>().ok().filter(|n| *n > 0)
+                            }
+                            ("step" | "back", None) => Some(1),
+                            _ => None,
+                        };
+                        let Some(count) = count else {
+                            scroll_text.add_line("Err: no valid step number found");
+                            continue;
+                        };
+                        let result = match tokens[0].as_str() {
+                            "step" => {
+                                (0..count).for_each(|_| prog.step_forward());
+                                Ok(())
+                            }
+                            "back" => (0..count).try_for_each(|_| prog.step_back()),
+                            _ => prog.goto_step(count),
+                        };
+                        match result {
+                            Ok(()) => {
+                                let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                write!(log_str, "Program at step {}", prog.get_state()).unwrap();
+                                scroll_text.add_line(&log_str);
+                            }
+                            Err(e) => scroll_text.add_line(e),
+                        }
+                    }
+                    "run" => {
+                        // run n [cycles|steps]
+                        let unit = match tokens.get(2).map(|t| t.as_str()) {
+                            None | Some("cycles") if num_tokens <= 3 => RunUnit::Cycles,
+                            Some("steps") if num_tokens == 3 => RunUnit::Steps,
+                            _ => {
+                                scroll_text.add_line("Err: 'run' needs a count and cycles/steps");
+                                continue;
+                            }
+                        };
+                        if prog.get_current_program() == 0 {
+                            scroll_text.add_line("Err: select a program with 'p' first");
+                            continue;
+                        }
+                        match tokens.get(1).map(|t| t.parse::<u32>()) {
+                            Some(Ok(count)) if count > 0 => {
+                                if let Err(e) = prog.start_run(count, unit) {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                prog.clock_set_auto(0, false);
+                                prog.clock_set_auto(1, false);
+                                for text in clock_mode_data_text.iter_mut() {
+                                    text.set(&"    ", now);
+                                }
+                                prog_mode_data_text.set(&mode_text(&prog.mode), now);
+                                run_next = 0;
+                                let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                write!(
+                                    log_str,
+                                    "Running {} {}",
+                                    count,
+                                    tokens.get(2).map_or("cycles", |t| t.as_str())
+                                )
+                                .unwrap();
+                                scroll_text.add_line(&log_str);
+                            }
+                            _ => scroll_text.add_line("Err: no valid count found"),
+                        }
+                    }
                     "a" => {
                         // a
                         if num_tokens > 1 {
@@ -606,15 +1265,12 @@ fn main() -> ! {
                                 ProgramMode::Manual => ProgramMode::Auto,
                                 ProgramMode::Auto => ProgramMode::Manual,
                                 ProgramMode::OneShot => ProgramMode::Auto,
+                                ProgramMode::Run => ProgramMode::Manual,
                             };
-                            prog.reset_state();
-                            prog_mode_data_text.set(
-                                &match prog.mode {
-                                    ProgramMode::Manual | ProgramMode::OneShot => "    ",
-                                    ProgramMode::Auto => "AUTO",
-                                },
-                                now,
-                            );
+                            if let Err(e) = prog.reset_state() {
+                                scroll_text.add_line(e);
+                            }
+                            prog_mode_data_text.set(&mode_text(&prog.mode), now);
                             if prog.mode == ProgramMode::Auto {
                                 prog.clock_set_auto(0, false);
                                 prog.clock_set_auto(1, false);
@@ -632,92 +1288,328 @@ fn main() -> ! {
                         }
                     }
 
-                    // f x
+                    // f 20kHz / f period 3ms
                     "f" => {
-                        if num_tokens != 2 {
-                            scroll_text.add_line("Err: 'f' command needs exactly 1 parameter");
+                        if !(2..=3).contains(&num_tokens) {
+                            scroll_text.add_line("Err: 'f' command needs a frequency or period");
                             continue;
                         }
                         if prog.get_current_program() > 0 {
-                            // is other a number?
-                            if let Ok(num) = &tokens[1].trim_end().parse::<u64>() {
-                                prog.set_freq(*num as u32);
-                                let mut log_str: String<PAGE_STR_WIDTH> =
-                                    String::from_str("Clock frequencies set to ").unwrap();
-                                write!(log_str, "{}", num).unwrap();
-                                log_str.push_str(" dHz").unwrap();
-                                scroll_text.add_line(&log_str);
-                                prog_freq_data_text.set(&(*num as i16), now);
-                                let _ = serial
-                                    .write(prog_freq_data_text.get_text().as_str().as_bytes());
-                            } else {
-                                scroll_text.add_line("Err: no valid frequency found");
+                            let result = join_tokens::<PAGE_STR_WIDTH>(&tokens[1..])
+                                .ok_or("Err: no valid frequency found")
+                                .and_then(|text| Frequency::parse(&text))
+                                .and_then(|freq| prog.set_freq(freq));
+                            match result {
+                                Ok(achieved) => {
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    write!(
+                                        log_str,
+                                        "Program frequency set to {} (runs at {})",
+                                        prog.prog_freq, achieved
+                                    )
+                                    .unwrap();
+                                    scroll_text.add_line(&log_str);
+                                    prog_freq_data_text.set(&freq_text(&prog.prog_freq), now);
+                                    let _ = serial
+                                        .write(prog_freq_data_text.get_text().as_str().as_bytes());
+                                }
+                                Err(e) => scroll_text.add_line(e),
                             }
                         } else {
                             scroll_text.add_line("Err: to set Frequency in Prog 0 use c/c1/c2 f");
                         }
                     }
 
+                    // trigger rise|fall prog / c1 burst n / out ch value, trigger off
+                    "trigger" => {
+                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                        if num_tokens == 2 && tokens[1].as_str() == "off" {
+                            trigger.set(None);
+                            scroll_text.add_line("Trigger off");
+                            continue;
+                        }
+                        if num_tokens > 1 {
+                            let edge = match tokens[1].as_str() {
+                                "rise" => Edge::Rising,
+                                "fall" => Edge::Falling,
+                                _ => {
+                                    scroll_text.add_line("Err: 'trigger' needs rise/fall or off");
+                                    continue;
+                                }
+                            };
+                            let words: Vec<&str, MAX_TOKENS> =
+                                tokens[2..].iter().map(|t| t.as_str()).collect();
+                            let action = match words.as_slice() {
+                                ["prog"] => Some(TriggerAction::OneShot),
+                                [clock @ ("c1" | "c2"), "burst", pulses] => pulses
+                                    .parse::<u32>()
+                                    .ok()
+                                    .map(|pulses| TriggerAction::Burst {
+                                        clock: if *clock == "c1" { 0 } else { 1 },
+                                        pulses,
+                                    }),
+                                ["out", channel, value] => channel
+                                    .parse::<usize>()
+                                    .ok()
+                                    .filter(|c| (1..=OUTPUT_CHANNELS).contains(c))
+                                    .zip(parse_value(value))
+                                    .map(|(channel, value)| TriggerAction::Output {
+                                        channel: channel - 1,
+                                        value,
+                                    }),
+                                _ => None,
+                            };
+                            let Some(action) = action else {
+                                scroll_text.add_line(
+                                    "Err: trigger action needs prog, c1/2 burst n or out ch value",
+                                );
+                                continue;
+                            };
+                            // checked here as it would only fail when the trigger fires
+                            if let TriggerAction::Burst { pulses, .. } = action {
+                                if !(1..=MAX_BURST).contains(&pulses) {
+                                    scroll_text.add_line(BURST_ERROR);
+                                    continue;
+                                }
+                            }
+                            trigger.set(Some((edge, action)));
+                        }
+                        match trigger.get() {
+                            Some((edge, action)) => {
+                                write!(log_str, "Trigger on {} edge: {}", edge, action).unwrap()
+                            }
+                            None => write!(log_str, "Trigger off").unwrap(),
+                        }
+                        scroll_text.add_line(&log_str);
+                    }
+
+                    // sync leader|follower|off
+                    "sync" => {
+                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                        if num_tokens > 2 {
+                            scroll_text.add_line("Err: too many tokens for 'sync'");
+                            continue;
+                        }
+                        if num_tokens == 2 {
+                            let mode = match tokens[1].as_str() {
+                                "leader" => SyncMode::Leader,
+                                "follower" => SyncMode::Follower,
+                                "off" => SyncMode::Off,
+                                _ => {
+                                    scroll_text
+                                        .add_line("Err: 'sync' needs leader, follower or off");
+                                    continue;
+                                }
+                            };
+                            match prog.set_sync_mode(mode) {
+                                Err(e) if prog.sync.mode != mode => {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                Err(e) => scroll_text.add_line(e),
+                                Ok(()) => {}
+                            }
+                            for channel in output_channels.iter_mut() {
+                                channel.set_synced(mode == SyncMode::Follower);
+                            }
+                        }
+                        match prog.sync.mode {
+                            SyncMode::Leader => {
+                                write!(log_str, "Sync leader: clock 1 and steps to GPIO27")
+                            }
+                            SyncMode::Follower => {
+                                write!(
+                                    log_str,
+                                    "Sync follower: clock 1, steps and outputs from GPIO26"
+                                )
+                            }
+                            SyncMode::Off => write!(log_str, "Sync off"),
+                        }
+                        .unwrap();
+                        scroll_text.add_line(&log_str);
+                    }
+
+                    // gate in[n] [high|low] / gate off
+                    "gate" => {
+                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                        if num_tokens > 3 {
+                            scroll_text.add_line("Err: too many tokens for 'gate'");
+                            continue;
+                        }
+                        if num_tokens == 2 && tokens[1].as_str() == "off" {
+                            prog.set_gate(None);
+                            scroll_text.add_line("Gate off");
+                            continue;
+                        }
+                        if num_tokens > 1 {
+                            let bit = parse_input_bits(&tokens[1])
+                                .filter(|(hi, lo, rest)| hi == lo && rest.is_empty());
+                            let level = match tokens.get(2).map(|t| t.as_str()) {
+                                None | Some("high") => Some(true),
+                                Some("low") => Some(false),
+                                _ => None,
+                            };
+                            let (Some((bit, _, _)), Some(level)) = (bit, level) else {
+                                scroll_text.add_line("Err: 'gate' needs in[n] [high|low] or off");
+                                continue;
+                            };
+                            prog.set_gate(Some((bit, level)));
+                        }
+                        match prog.get_gate() {
+                            Some((bit, level)) => write!(
+                                log_str,
+                                "Gate on in[{}], auto clocks and programs pause while {}",
+                                bit,
+                                if level { "high" } else { "low" }
+                            )
+                            .unwrap(),
+                            None => write!(log_str, "Gate off").unwrap(),
+                        }
+                        scroll_text.add_line(&log_str);
+                    }
+
+                    // break in == v / break in[n] == 1 / break in changes / break clear [n]
+                    "break" => {
+                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                        if num_tokens == 1 {
+                            log_str.push_str("Breakpoints:").unwrap();
+                            for (i, condition) in breakpoints.iter().enumerate() {
+                                write!(log_str, " {} {},", i + 1, condition).unwrap();
+                            }
+                            scroll_text.add_line(log_str.trim_end_matches(','));
+                            continue;
+                        }
+                        if tokens[1].as_str() == "clear" {
+                            let number = match num_tokens {
+                                2 => None,
+                                3 => match tokens[2].parse::<usize>() {
+                                    Ok(n) => Some(n),
+                                    Err(_) => {
+                                        scroll_text
+                                            .add_line("Err: no valid breakpoint number found");
+                                        continue;
+                                    }
+                                },
+                                _ => {
+                                    scroll_text.add_line("Err: too many tokens for 'break clear'");
+                                    continue;
+                                }
+                            };
+                            if let Err(e) = breakpoints.clear(number) {
+                                scroll_text.add_line(e);
+                                continue;
+                            }
+                            scroll_text.add_line("Breakpoints cleared");
+                            continue;
+                        }
+                        let result = join_tokens::<PAGE_STR_WIDTH>(&tokens[1..])
+                            .ok_or("Err: breakpoint too long")
+                            .and_then(|text| Condition::parse(&text))
+                            .and_then(|condition| breakpoints.add(condition, input_channel.data));
+                        match result {
+                            Ok((number, holds)) => {
+                                write!(
+                                    log_str,
+                                    "Breakpoint {} set: {}{}",
+                                    number,
+                                    breakpoints.iter().nth(number - 1).unwrap(),
+                                    if holds {
+                                        " (true now, halts when true again)"
+                                    } else {
+                                        ""
+                                    }
+                                )
+                                .unwrap();
+                                scroll_text.add_line(&log_str);
+                            }
+                            Err(e) => scroll_text.add_line(e),
+                        }
+                    }
+
                     "c" => {
                         if num_tokens == 1 {
                             scroll_text.add_line("Err: 'c' command needs minimum 1 parameter");
                             continue;
                         }
+                        // counters also count program steps, so not only in Prog 0
+                        if tokens[1].as_str() == "reset" {
+                            for clock in prog.clocks.iter_mut() {
+                                clock.reset_cycles();
+                            }
+                            count_shown = [None; 2];
+                            scroll_text.add_line("Clock cycle counters reset");
+                            continue;
+                        }
 
                         if prog.get_current_program() == 0 {
                             match tokens[1].as_str() {
-                                "s" | "so" | "a" => {
-                                    if num_tokens != 2 {
-                                        scroll_text.add_line(
-                                            "Err: 'c s/s0/a' commands do not accept parameters",
-                                        );
-                                        continue;
-                                    }
+                                "s" | "so" | "a" if num_tokens != 2 => {
+                                    scroll_text.add_line(
+                                        "Err: 'c s/s0/a' commands do not accept parameters",
+                                    );
+                                    continue;
                                 }
-                                "f" => {
-                                    if num_tokens != 3 {
-                                        scroll_text
-                                            .add_line("Err: 'c f' command needs 1 parameters");
-                                        continue;
-                                    }
+                                "f" if !(3..=4).contains(&num_tokens) => {
+                                    scroll_text
+                                        .add_line("Err: 'c f' command needs a frequency or period");
+                                    continue;
                                 }
                                 _ => {}
                             }
 
                             match tokens[1].as_str() {
                                 "s" => {
-                                    prog.clocks_sync();
+                                    if let Err(e) = prog.clocks_sync() {
+                                        scroll_text.add_line(e);
+                                    }
                                 }
                                 "so" => {
-                                    prog.clocks_sync_opposite();
+                                    if let Err(e) = prog.clocks_sync_opposite() {
+                                        scroll_text.add_line(e);
+                                    }
                                 }
                                 "a" => {
                                     for i in 0..2 {
                                         prog.clock_toggle_auto(i);
-                                        clock_mode_data_text[i].set(
-                                            &match prog.clocks[i].mode {
-                                                ClockMode::Manual => "    ",
-                                                ClockMode::Auto => "AUTO",
-                                            },
-                                            now,
-                                        );
+                                        clock_mode_data_text[i]
+                                            .set(&clock_mode_text(&prog.clocks[i].mode), now);
                                         let _ = serial.write(
                                             clock_mode_data_text[i].get_text().as_str().as_bytes(),
                                         );
                                     }
                                 }
                                 "f" => {
-                                    // is other a number?
-                                    if let Ok(num) = &tokens[2].trim_end().parse::<u64>() {
-                                        for i in 0..2 {
-                                            prog.clock_set_freq(i, num);
+                                    let freq = match join_tokens::<PAGE_STR_WIDTH>(&tokens[2..])
+                                        .ok_or("Err: no valid frequency found")
+                                        .and_then(|text| Frequency::parse(&text))
+                                    {
+                                        Ok(freq) => freq,
+                                        Err(e) => {
+                                            scroll_text.add_line(e);
+                                            continue;
                                         }
-                                    } else {
-                                        scroll_text.add_line("Err: no valid frequency found");
+                                    };
+                                    for (i, text) in clock_freq_data_text.iter_mut().enumerate() {
+                                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                        match prog.clock_set_freq(i, freq) {
+                                            Ok(achieved) => write!(
+                                                log_str,
+                                                "Clock {} set to {} (runs at {})",
+                                                i + 1,
+                                                freq,
+                                                achieved
+                                            )
+                                            .unwrap(),
+                                            Err(e) => {
+                                                write!(log_str, "{} (clock {})", e, i + 1).unwrap()
+                                            }
+                                        }
+                                        scroll_text.add_line(&log_str);
+                                        text.set(&freq_text(&prog.clocks[i].freq), now);
                                     }
                                 }
                                 _ => {
-                                    scroll_text.add_line("Err: 'c' command needs s/s0/a/f");
+                                    scroll_text.add_line("Err: 'c' command needs s/s0/a/f/reset");
                                     continue;
                                 }
                             }
@@ -727,22 +1619,44 @@ fn main() -> ! {
                     }
 
                     "c1" | "c2" => {
+                        if num_tokens == 1 {
+                            scroll_text.add_line("Err: 'c1/2' command needs minimum 1 parameter");
+                            continue;
+                        }
+                        if tokens[1].as_str() == "reset" {
+                            let clock_index = if tokens[0].as_str() == "c1" { 0 } else { 1 };
+                            let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                            write!(
+                                log_str,
+                                "Clock {} cycle counter reset at {}",
+                                clock_index + 1,
+                                prog.clocks[clock_index].get_cycles(now)
+                            )
+                            .unwrap();
+                            prog.clocks[clock_index].reset_cycles();
+                            count_shown[clock_index] = None;
+                            scroll_text.add_line(&log_str);
+                            continue;
+                        }
                         if prog.get_current_program() == 0 {
                             match tokens[1].as_str() {
-                                "a" => {
-                                    if num_tokens != 2 {
-                                        scroll_text.add_line(
-                                            "Err: 'c1/2 a' command does not accept parameters",
-                                        );
-                                        continue;
-                                    }
+                                "a" | "step" | "high" | "low" if num_tokens != 2 => {
+                                    scroll_text.add_line(
+                                        "Err: 'c1/2 a/step/high/low' command does not accept parameters",
+                                    );
+                                    continue;
                                 }
-                                "f" => {
-                                    if num_tokens != 3 {
-                                        scroll_text
-                                            .add_line("Err: 'c1/2 f' command needs 1 parameter");
-                                        continue;
-                                    }
+                                "f" if !(3..=4).contains(&num_tokens) => {
+                                    scroll_text.add_line(
+                                        "Err: 'c1/2 f' command needs a frequency or period",
+                                    );
+                                    continue;
+                                }
+                                "duty" | "phase" | "hw" | "burst" | "pulse" if num_tokens != 3 => {
+                                    scroll_text.add_line(
+                                        "Err: 'c1/2 duty/phase/hw/burst/pulse' command needs 1 parameter",
+                                    );
+                                    continue;
                                 }
                                 _ => {}
                             }
@@ -756,23 +1670,679 @@ fn main() -> ! {
                                 // cx a
                                 "a" => {
                                     prog.clock_toggle_auto(clock_index);
+                                    clock_mode_data_text[clock_index]
+                                        .set(&clock_mode_text(&prog.clocks[clock_index].mode), now);
                                 }
                                 // cx f y
                                 "f" => {
-                                    // is other a number?
-                                    if let Ok(num) = &tokens[2].trim_end().parse::<u64>() {
-                                        prog.clock_set_freq(clock_index, num);
+                                    let result = join_tokens::<PAGE_STR_WIDTH>(&tokens[2..])
+                                        .ok_or("Err: no valid frequency found")
+                                        .and_then(|text| Frequency::parse(&text))
+                                        .and_then(|freq| prog.clock_set_freq(clock_index, freq));
+                                    match result {
+                                        Ok(achieved) => {
+                                            let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                            write!(
+                                                log_str,
+                                                "Clock {} set to {} (runs at {})",
+                                                clock_index + 1,
+                                                prog.clocks[clock_index].freq,
+                                                achieved
+                                            )
+                                            .unwrap();
+                                            scroll_text.add_line(&log_str);
+                                            clock_freq_data_text[clock_index].set(
+                                                &freq_text(&prog.clocks[clock_index].freq),
+                                                now,
+                                            );
+                                        }
+                                        Err(e) => scroll_text.add_line(e),
+                                    }
+                                }
+                                // cx duty %
+                                "duty" => {
+                                    let result = match tokens[2].trim_end_matches('%').parse::<u8>()
+                                    {
+                                        Ok(duty) => prog.clock_set_duty(clock_index, duty),
+                                        Err(_) => Err("Err: duty cycle needs 1-99 %"),
+                                    };
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    match result {
+                                        Ok(()) => {
+                                            write!(
+                                                log_str,
+                                                "Clock {} duty cycle set to {}%",
+                                                clock_index + 1,
+                                                prog.clocks[clock_index].duty
+                                            )
+                                            .unwrap();
+                                            scroll_text.add_line(&log_str);
+                                        }
+                                        Err(e) => scroll_text.add_line(e),
+                                    }
+                                }
+                                // cx phase degrees / cx phase µs us
+                                "phase" => {
+                                    let phase = if let Some(micros) = tokens[2].strip_suffix("us") {
+                                        micros.parse::<u64>().ok().map(Phase::Micros)
                                     } else {
-                                        scroll_text.add_line("Err: no valid frequency found");
+                                        match tokens[2].trim_end_matches("deg").parse::<u16>() {
+                                            Ok(degrees) if degrees < 360 => {
+                                                Some(Phase::Degrees(degrees))
+                                            }
+                                            _ => None,
+                                        }
+                                    };
+                                    let Some(phase) = phase else {
+                                        scroll_text.add_line(
+                                            "Err: phase needs 0-359 degrees or a time in us",
+                                        );
+                                        continue;
+                                    };
+                                    if let Err(e) = prog.clock_set_phase(clock_index, &phase, now) {
+                                        scroll_text.add_line(e);
+                                        continue;
+                                    }
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    write!(
+                                        log_str,
+                                        "Clock {} phase set to {} to clock {}",
+                                        clock_index + 1,
+                                        tokens[2],
+                                        2 - clock_index
+                                    )
+                                    .unwrap();
+                                    scroll_text.add_line(&log_str);
+                                }
+                                // cx step / cx high / cx low
+                                "step" | "high" | "low" => {
+                                    let state = match tokens[1].as_str() {
+                                        "high" => true,
+                                        "low" => false,
+                                        _ => !prog.clocks[clock_index].state,
+                                    };
+                                    if let Err(e) = prog.clock_set_level(clock_index, state) {
+                                        scroll_text.add_line(e);
+                                        continue;
+                                    }
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    write!(
+                                        log_str,
+                                        "Clock {} {}",
+                                        clock_index + 1,
+                                        if state { "high" } else { "low" }
+                                    )
+                                    .unwrap();
+                                    scroll_text.add_line(&log_str);
+                                }
+                                // cx pulse µs
+                                "pulse" => {
+                                    let result = match tokens[2]
+                                        .trim_end_matches("us")
+                                        .parse::<u64>()
+                                    {
+                                        Ok(micros) => prog.clock_pulse(clock_index, micros, now),
+                                        Err(_) => Err("Err: pulse needs a length in us"),
+                                    };
+                                    if let Err(e) = result {
+                                        scroll_text.add_line(e);
+                                        continue;
+                                    }
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    write!(
+                                        log_str,
+                                        "Clock {} high for {} us",
+                                        clock_index + 1,
+                                        tokens[2].trim_end_matches("us")
+                                    )
+                                    .unwrap();
+                                    scroll_text.add_line(&log_str);
+                                }
+                                // cx burst n
+                                "burst" => {
+                                    let result = match tokens[2].parse::<u32>() {
+                                        Ok(pulses) => {
+                                            prog.clock_start_burst(clock_index, pulses, now)
+                                        }
+                                        Err(_) => Err(BURST_ERROR),
+                                    };
+                                    if let Err(e) = result {
+                                        scroll_text.add_line(e);
+                                        continue;
+                                    }
+                                    burst_shown[clock_index] = true;
+                                    count_next = 0; // show the counter right away
+                                    clock_mode_data_text[clock_index]
+                                        .set(&clock_mode_text(&prog.clocks[clock_index].mode), now);
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    write!(
+                                        log_str,
+                                        "Clock {} burst of {} pulses at {}",
+                                        clock_index + 1,
+                                        tokens[2],
+                                        prog.clocks[clock_index].freq
+                                    )
+                                    .unwrap();
+                                    scroll_text.add_line(&log_str);
+                                }
+                                // cx hw on/off
+                                "hw" => {
+                                    let hardware = match tokens[2].as_str() {
+                                        "on" => true,
+                                        "off" => false,
+                                        _ => {
+                                            scroll_text.add_line("Err: 'c1/2 hw' needs on/off");
+                                            continue;
+                                        }
+                                    };
+                                    let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                    match prog.clock_set_hardware(clock_index, hardware) {
+                                        Ok((freq, true)) => write!(
+                                            log_str,
+                                            "Clock {} from PIO in auto mode, runs at {}",
+                                            clock_index + 1,
+                                            freq
+                                        )
+                                        .unwrap(),
+                                        Ok((freq, false)) => write!(
+                                            log_str,
+                                            "Clock {} in software{}, runs at {}",
+                                            clock_index + 1,
+                                            if hardware { " below 10 Hz" } else { "" },
+                                            freq
+                                        )
+                                        .unwrap(),
+                                        Err(e) => {
+                                            scroll_text.add_line(e);
+                                            continue;
+                                        }
                                     }
+                                    scroll_text.add_line(&log_str);
                                 }
                                 _ => {
-                                    scroll_text.add_line("Err: 'c1/2' command needs a/f");
+                                    scroll_text.add_line(
+                                        "Err: 'c1/2' command needs a/f/duty/phase/hw/burst/step/pulse/high/low/reset",
+                                    );
                                     continue;
                                 }
                             }
                         }
                     }
+                    "in" => {
+                        // in def [name:bit name:lo-hi ...]
+                        if num_tokens == 1 || tokens[1].as_str() != "def" {
+                            scroll_text.add_line("Err: 'in' command needs def");
+                            continue;
+                        }
+                        match FieldLayout::parse(&tokens[2..]) {
+                            Ok(layout) => {
+                                input_field_layout = layout;
+                                input_channel_data_text.data_text[4]
+                                    .set(&input_field_layout.decode(input_channel.data), now);
+                                scroll_text.add_line("Input fields defined");
+                            }
+                            Err(e) => scroll_text.add_line(e),
+                        }
+                    }
+                    "save" | "load" => {
+                        // save/load name
+                        if num_tokens == 1 {
+                            scroll_text.add_line("Err: 'save/load' command needs a name");
+                            continue;
+                        }
+                        let Some(name) = join_tokens::<PRESET_NAME_WIDTH>(&tokens[1..]) else {
+                            scroll_text.add_line("Err: preset name too long");
+                            continue;
+                        };
+                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                        if tokens[0].as_str() == "save" {
+                            let preset = Preset {
+                                name: name.clone(),
+                                channel_data: from_fn(|i| output_channels[i].get()),
+                                channel_reverse: from_fn(|i| output_channels[i].is_reversed()),
+                                clock_auto: from_fn(|i| {
+                                    matches!(prog.clocks[i].mode, ClockMode::Auto)
+                                }),
+                                clock_freq: from_fn(|i| prog.clocks[i].freq),
+                                program: Some(prog.get_current_program()),
+                                prog_auto: prog.mode == ProgramMode::Auto,
+                                prog_freq: prog.prog_freq,
+                            };
+                            if let Err(e) = presets.save(preset) {
+                                scroll_text.add_line(e);
+                                continue;
+                            }
+                            write!(log_str, "Preset '{}' saved", name).unwrap();
+                        } else {
+                            let Some(preset) = presets.find(&name) else {
+                                scroll_text.add_line("Err: preset not found");
+                                continue;
+                            };
+                            let Some(program) = preset.program else {
+                                scroll_text.add_line("Err: the program of this preset was deleted");
+                                continue;
+                            };
+                            if !prog.set_program(program) {
+                                scroll_text.add_line("Err: preset program does not exist");
+                                continue;
+                            }
+                            // a frequency out of range for the current clock mode is kept
+                            if let Err(e) = prog.set_freq(preset.prog_freq) {
+                                scroll_text.add_line(e);
+                            }
+                            if preset.prog_auto && program > 0 {
+                                prog.mode = ProgramMode::Auto;
+                                if let Err(e) = prog.reset_state() {
+                                    scroll_text.add_line(e);
+                                }
+                            }
+                            prog_number_data_text.set(&(program as i16), now);
+                            prog_name_data_text.set(prog.get_current_program_name(), now);
+                            prog_mode_data_text.set(&mode_text(&prog.mode), now);
+                            prog_freq_data_text.set(&freq_text(&prog.prog_freq), now);
+                            for i in 0..2 {
+                                if let Err(e) = prog.clock_set_freq(i, preset.clock_freq[i]) {
+                                    scroll_text.add_line(e);
+                                }
+                                prog.clock_set_auto(i, preset.clock_auto[i]);
+                                clock_mode_data_text[i]
+                                    .set(&clock_mode_text(&prog.clocks[i].mode), now);
+                                clock_freq_data_text[i].set(&freq_text(&prog.clocks[i].freq), now);
+                            }
+                            for (i, channel) in output_channels.iter_mut().enumerate() {
+                                if channel.is_reversed() != preset.channel_reverse[i] {
+                                    channel.reverse();
+                                }
+                                channel.set(preset.channel_data[i]);
+                                output_channel_data_text[i]
+                                    .show_reverse(channel.is_reversed(), now);
+                                output_channel_data_text[i].show(
+                                    channel.get(),
+                                    &output_field_layouts[i],
+                                    now,
+                                );
+                            }
+                            write!(log_str, "Preset '{}' loaded", name).unwrap();
+                        }
+                        scroll_text.add_line(&log_str);
+                    }
+                    "presets" => {
+                        // presets
+                        if num_tokens > 1 {
+                            scroll_text
+                                .add_line("Err: 'presets' command does not accept parameters");
+                            continue;
+                        }
+                        let mut log_str: String<PAGE_STR_WIDTH> =
+                            String::from_str("Presets:").unwrap();
+                        let mut count = 0;
+                        for preset in presets.iter() {
+                            if log_str.len() + preset.name.len() + 3 > PAGE_WIDTH - 2 {
+                                scroll_text.add_line(&log_str);
+                                log_str = String::from_str("        ").unwrap();
+                            }
+                            write!(log_str, " '{}'", preset.name).unwrap();
+                            count += 1;
+                        }
+                        if count == 0 {
+                            scroll_text.add_line("No presets saved");
+                        } else {
+                            scroll_text.add_line(&log_str);
+                        }
+                    }
+                    "cfg" => {
+                        // cfg save / cfg factory
+                        if num_tokens != 2 {
+                            scroll_text.add_line("Err: 'cfg' command needs save/factory");
+                            continue;
+                        }
+                        match tokens[1].as_str() {
+                            "save" => {
+                                let result = settings_store.save(&Settings {
+                                    prog: &mut prog,
+                                    output_channels: &mut output_channels,
+                                    output_field_layouts: &mut output_field_layouts,
+                                    input_field_layout: &mut input_field_layout,
+                                    presets: &mut presets,
+                                });
+                                scroll_text.add_line(match result {
+                                    Ok(true) => "Settings saved to flash",
+                                    Ok(false) => "Settings unchanged, nothing written",
+                                    Err(_) => "Err: settings too large for flash slot",
+                                });
+                            }
+                            "factory" => {
+                                // erase stored settings and restart with defaults
+                                settings_store.erase_all();
+                                cortex_m::peripheral::SCB::sys_reset();
+                            }
+                            _ => {
+                                scroll_text.add_line("Err: 'cfg' command needs save/factory");
+                            }
+                        }
+                    }
+                    "prog" => {
+                        // prog new name sequence / edit x sequence / append x sequence
+                        // prog rename x name / del x / show x
+                        // prog time x [us|rel] 1,4,1,1 / time x step=duration ...
+                        // prog put x step channel value / sample x step / clear x [step]
+                        // prog label x step [name] / log [clear]
+                        // prog import name / export [x|log]
+                        if tokens.get(1).map(|t| t.as_str()) == Some("import") {
+                            if num_tokens != 3 {
+                                scroll_text.add_line("Err: 'prog import' needs a program name");
+                            } else if prog.number_of_programs() >= MAX_PROGRAMS {
+                                scroll_text.add_line("Err: program list is full");
+                            } else {
+                                match program_name(&tokens[2]) {
+                                    Ok(name) => {
+                                        import_name = Some(name);
+                                        scroll_text
+                                            .add_line("Paste WaveDrom JSON now, Esc cancels");
+                                    }
+                                    Err(e) => scroll_text.add_line(e),
+                                }
+                            }
+                            continue;
+                        }
+                        if tokens.get(1).map(|t| t.as_str()) == Some("export") {
+                            let number = match tokens.get(2).map(|t| t.as_str()) {
+                                None => Some(prog.get_current_program()),
+                                Some("log") => None,
+                                Some(t) => match t.parse::<usize>() {
+                                    Ok(n) if n < prog.number_of_programs() => Some(n),
+                                    _ => {
+                                        scroll_text.add_line("Err: no valid program number found");
+                                        continue;
+                                    }
+                                },
+                            };
+                            let mut writer = SerialWriter {
+                                usb_dev: &mut usb_dev,
+                                serial: &mut serial,
+                                timer,
+                            };
+                            let _ = write!(writer, "\x1B[{};1H\x1B[J", DUMP_LINE);
+                            let _ = match number {
+                                Some(n) => wavedrom::wavedrom::export_program(
+                                    &mut writer,
+                                    prog.get_program(n).unwrap(),
+                                ),
+                                None => {
+                                    wavedrom::wavedrom::export_log(&mut writer, prog.get_step_log())
+                                }
+                            };
+                            scroll_text.add_line("WaveDrom JSON written below the dashboard");
+                            continue;
+                        }
+                        if tokens.get(1).map(|t| t.as_str()) == Some("log") {
+                            match tokens.get(2).map(|t| t.as_str()) {
+                                None => {
+                                    let mut log_str: String<PAGE_STR_WIDTH> =
+                                        String::from_str("Step log:").unwrap();
+                                    for (step, data) in prog.get_step_log() {
+                                        if write!(log_str, " {}:0x{:04X}", step, *data as u16)
+                                            .is_err()
+                                        {
+                                            break;
+                                        }
+                                    }
+                                    scroll_text.add_line(&log_str);
+                                }
+                                Some("clear") => {
+                                    prog.clear_step_log();
+                                    scroll_text.add_line("Step log cleared");
+                                }
+                                _ => scroll_text.add_line("Err: 'prog log' only knows 'clear'"),
+                            }
+                            continue;
+                        }
+                        let expected_tokens = match tokens.get(1).map(|t| t.as_str()) {
+                            Some("new" | "edit" | "append" | "rename" | "sample") => 4,
+                            Some("del" | "show") => 3,
+                            Some("put") => 6,
+                            Some("label") if num_tokens == 4 || num_tokens == 5 => num_tokens,
+                            Some("clear") if (3..=4).contains(&num_tokens) => num_tokens,
+                            Some("time") if num_tokens >= 4 => num_tokens,
+                            _ => {
+                                scroll_text.add_line(
+                                    "Err: 'prog' needs new/edit/append/time/put/sample/clear/label/log/rename/del/show",
+                                );
+                                continue;
+                            }
+                        };
+                        if num_tokens != expected_tokens {
+                            scroll_text.add_line(
+                                "Err: 'prog new/edit/append/rename' need 2, 'prog del/show' 1 parameter",
+                            );
+                            continue;
+                        }
+                        let number = if tokens[1].as_str() == "new" {
+                            prog.number_of_programs()
+                        } else {
+                            match tokens[2].parse::<usize>() {
+                                Ok(n) if n > 0 && n < prog.number_of_programs() => n,
+                                _ => {
+                                    scroll_text.add_line("Err: no valid program number found");
+                                    continue;
+                                }
+                            }
+                        };
+                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                        match tokens[1].as_str() {
+                            "new" => {
+                                if number >= MAX_PROGRAMS {
+                                    scroll_text.add_line("Err: program list is full");
+                                    continue;
+                                }
+                                let name = match program_name(&tokens[2]) {
+                                    Ok(name) => name,
+                                    Err(e) => {
+                                        scroll_text.add_line(e);
+                                        continue;
+                                    }
+                                };
+                                match Program::parse_sequence(&tokens[3]) {
+                                    Ok((signals, steps)) => {
+                                        prog.add_program(Program::new(name, signals, steps));
+                                    }
+                                    Err(e) => {
+                                        scroll_text.add_line(e);
+                                        continue;
+                                    }
+                                }
+                                write!(log_str, "Program {} '{}' added", number, tokens[2])
+                                    .unwrap();
+                            }
+                            "edit" => match Program::parse_sequence(&tokens[3]) {
+                                Ok((signals, steps)) => {
+                                    prog.edit_program(number, signals, steps);
+                                    write!(log_str, "Program {} changed", number).unwrap();
+                                }
+                                Err(e) => {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                            },
+                            "append" => {
+                                if let Err(e) = Program::parse_sequence(&tokens[3]).and_then(
+                                    |(signals, steps)| prog.append_program(number, signals, steps),
+                                ) {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                write!(
+                                    log_str,
+                                    "Program {} has {} steps",
+                                    number,
+                                    prog.get_program(number).unwrap().get_sequence_length()
+                                )
+                                .unwrap();
+                            }
+                            "time" => {
+                                let result = if tokens[3].contains('=') {
+                                    // single steps, keeps the timing unit, all are
+                                    // checked before any is changed
+                                    let durations: Option<Vec<(usize, u32), MAX_TOKENS>> = tokens
+                                        [3..]
+                                        .iter()
+                                        .map(|t| {
+                                            let (step, duration) = t.split_once('=')?;
+                                            let step = step.parse::<usize>().ok()?;
+                                            let duration = duration.parse::<u32>().ok()?;
+                                            (duration > 0).then_some((step, duration))
+                                        })
+                                        .collect();
+                                    match durations {
+                                        Some(durations) => {
+                                            prog.set_program_step_durations(number, &durations)
+                                        }
+                                        None => Err("Err: steps need the form step=duration"),
+                                    }
+                                } else {
+                                    let (timing, list) = match tokens[3].as_str() {
+                                        "us" => (Some(StepTiming::Micros), tokens.get(4)),
+                                        "rel" => (Some(StepTiming::Relative), tokens.get(4)),
+                                        _ => (None, tokens.get(3)),
+                                    };
+                                    match list {
+                                        Some(list) if num_tokens == 4 + timing.is_some() as usize => {
+                                            Program::parse_durations(list).and_then(|durations| {
+                                                prog.set_program_durations(
+                                                    number, timing, &durations,
+                                                )
+                                            })
+                                        }
+                                        _ => Err("Err: 'prog time' needs [us|rel] and a list of durations"),
+                                    }
+                                };
+                                if let Err(e) = result {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                write!(
+                                    log_str,
+                                    "Program {} timing: {}",
+                                    number,
+                                    prog.get_program(number).unwrap().get_timing_text()
+                                )
+                                .unwrap();
+                            }
+                            "put" | "sample" => {
+                                let step = tokens[3].parse::<u16>().ok();
+                                let kind = if tokens[1].as_str() == "put" {
+                                    match (tokens[4].parse::<u8>(), parse_value(&tokens[5])) {
+                                        (Ok(channel @ 1..), Some(value)) => Some(ActionKind::Put {
+                                            channel: channel - 1,
+                                            value,
+                                        }),
+                                        _ => None,
+                                    }
+                                } else {
+                                    Some(ActionKind::Sample)
+                                };
+                                let result = match (step, kind) {
+                                    (Some(step), Some(kind)) => {
+                                        prog.add_program_action(number, StepAction { step, kind })
+                                    }
+                                    _ => Err("Err: no valid step, channel or value found"),
+                                };
+                                if let Err(e) = result {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                write!(
+                                    log_str,
+                                    "Program {} actions: {}",
+                                    number,
+                                    prog.get_program(number).unwrap().get_actions_text()
+                                )
+                                .unwrap();
+                            }
+                            "label" => {
+                                let result = match tokens[3].parse::<u16>() {
+                                    Ok(step) => prog.set_program_label(
+                                        number,
+                                        step,
+                                        tokens.get(4).map(|t| t.as_str()),
+                                    ),
+                                    Err(_) => Err("Err: no valid step number found"),
+                                };
+                                if let Err(e) = result {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                write!(
+                                    log_str,
+                                    "Program {} labels: {}",
+                                    number,
+                                    prog.get_program(number).unwrap().get_labels_text()
+                                )
+                                .unwrap();
+                            }
+                            "clear" => {
+                                let step = match tokens.get(3).map(|t| t.parse::<u16>()) {
+                                    None => None,
+                                    Some(Ok(step)) => Some(step),
+                                    Some(Err(_)) => {
+                                        scroll_text.add_line("Err: no valid step number found");
+                                        continue;
+                                    }
+                                };
+                                if let Err(e) = prog.clear_program_actions(number, step) {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                                write!(log_str, "Program {} actions cleared", number).unwrap();
+                            }
+                            "rename" => match program_name(&tokens[3]) {
+                                Ok(name) => {
+                                    prog.rename_program(number, name);
+                                    write!(log_str, "Program {} renamed", number).unwrap();
+                                }
+                                Err(e) => {
+                                    scroll_text.add_line(e);
+                                    continue;
+                                }
+                            },
+                            "del" => {
+                                prog.delete_program(number);
+                                write!(log_str, "Program {} deleted", number).unwrap();
+                                match presets.program_deleted(number) {
+                                    0 => {}
+                                    1 => write!(log_str, ", 1 preset lost its program").unwrap(),
+                                    n => write!(log_str, ", {} presets lost their program", n)
+                                        .unwrap(),
+                                }
+                            }
+                            _ => {
+                                let program = prog.get_program(number).unwrap();
+                                write!(
+                                    log_str,
+                                    "Program {} {} ({} signals, {} steps): {}",
+                                    number,
+                                    program.get_name().trim_end(),
+                                    program.get_signal_count(),
+                                    program.get_sequence_length(),
+                                    program.get_sequence_text()
+                                )
+                                .unwrap();
+                                scroll_text.add_line(&log_str);
+                                log_str.clear();
+                                write!(log_str, "Timing: {}", program.get_timing_text()).unwrap();
+                                scroll_text.add_line(&log_str);
+                                log_str.clear();
+                                write!(log_str, "Actions: {}", program.get_actions_text()).unwrap();
+                                scroll_text.add_line(&log_str);
+                                log_str.clear();
+                                write!(log_str, "Labels: {}", program.get_labels_text()).unwrap();
+                            }
+                        }
+                        scroll_text.add_line(&log_str);
+                        prog_number_data_text.set(&(prog.get_current_program() as i16), now);
+                        prog_name_data_text.set(prog.get_current_program_name(), now);
+                        prog_mode_data_text.set(&mode_text(&prog.mode), now);
+                    }
                     "p" => {
                         // p x
                         if num_tokens != 2 {
@@ -780,11 +2350,10 @@ fn main() -> ! {
                             continue;
                         }
                         if let Ok(num) = &tokens[1].trim_end().parse::<u8>() {
-                            if *num > prog.number_of_programs() as u8 {
+                            if *num >= prog.number_of_programs() as u8 {
                                 scroll_text.add_line("Err: no valid program number found");
                             } else {
                                 prog.set_program(*num as usize);
-                                prog.reset_state();
                                 prog.mode = ProgramMode::Manual;
                                 prog_mode_data_text.set(&"    ", now);
                                 prog_number_data_text.set(&(*num as i16), now);
@@ -822,7 +2391,7 @@ fn main() -> ! {
                                     scroll_text.add_line("Err: 'channel' command needs parameters");
                                     continue;
                                 }
-                                match tokens[1].as_str() {
+                                let value: i16 = match tokens[1].as_str() {
                                     // reverse bit order
                                     // channel r
                                     "r" => {
@@ -833,51 +2402,241 @@ fn main() -> ! {
                                             continue;
                                         }
                                         output_channels[i].reverse();
-                                        let mut rev_str: String<PAGE_STR_WIDTH> = String::new();
-                                        match output_channels[i].is_reversed() {
-                                            true => {
-                                                rev_str.push_str("R").unwrap();
-                                            }
-                                            false => {
-                                                rev_str.push_str(" ").unwrap();
-                                            }
-                                        }
-                                        output_channel_data_text[i].data_text[3].set(&rev_str, now);
+                                        output_channel_data_text[i]
+                                            .show_reverse(output_channels[i].is_reversed(), now);
                                         let _ = serial.write(
                                             output_channel_data_text[i].data_text[3]
                                                 .get_text()
                                                 .as_str()
                                                 .as_bytes(),
                                         );
+                                        continue;
                                     }
-                                    // channel value
-                                    _ => {
-                                        if num_tokens != 2 {
+                                    // queue values shifted out one after the other
+                                    // channel q v1 v2 .. / q clear / q pace now|c1|c2|20ms
+                                    "q" => {
+                                        let mut log_str: String<PAGE_STR_WIDTH> = String::new();
+                                        match tokens.get(2).map(|t| t.as_str()) {
+                                            None => write!(
+                                                log_str,
+                                                "Channel {} queue {}/{}, {}",
+                                                i + 1,
+                                                output_channels[i].queue_len(),
+                                                QUEUE_LENGTH,
+                                                output_channels[i].pace
+                                            )
+                                            .unwrap(),
+                                            Some("clear") => {
+                                                output_channels[i].clear_queue();
+                                                write!(log_str, "Channel {} queue cleared", i + 1)
+                                                    .unwrap();
+                                            }
+                                            Some("pace") => {
+                                                if num_tokens != 4 {
+                                                    scroll_text.add_line(
+                                                        "Err: 'q pace' needs now, c1, c2 or a delay",
+                                                    );
+                                                    continue;
+                                                }
+                                                match Pace::parse(&tokens[3]) {
+                                                    Ok(pace) => output_channels[i].pace = pace,
+                                                    Err(e) => {
+                                                        scroll_text.add_line(e);
+                                                        continue;
+                                                    }
+                                                }
+                                                write!(
+                                                    log_str,
+                                                    "Channel {} queue: {}",
+                                                    i + 1,
+                                                    output_channels[i].pace
+                                                )
+                                                .unwrap();
+                                                // PIO edges are estimated from the time
+                                                if let Pace::Clock(c) = output_channels[i].pace {
+                                                    if prog.clocks[c].is_hardware() {
+                                                        write!(log_str, " (approx., PIO)").unwrap();
+                                                    }
+                                                }
+                                            }
+                                            Some(_) => {
+                                                let values: Option<Vec<i16, MAX_TOKENS>> = tokens
+                                                    [2..]
+                                                    .iter()
+                                                    .map(|t| parse_value(t))
+                                                    .collect();
+                                                let Some(values) = values else {
+                                                    scroll_text
+                                                        .add_line("no valid 16bit number found");
+                                                    continue;
+                                                };
+                                                let queued = values
+                                                    .iter()
+                                                    .take_while(|v| output_channels[i].queue(**v))
+                                                    .count();
+                                                write!(
+                                                    log_str,
+                                                    "Channel {} queued {} values, {}/{}{}",
+                                                    i + 1,
+                                                    queued,
+                                                    output_channels[i].queue_len(),
+                                                    QUEUE_LENGTH,
+                                                    if queued < values.len() {
+                                                        ", queue full"
+                                                    } else {
+                                                        ""
+                                                    }
+                                                )
+                                                .unwrap();
+                                            }
+                                        }
+                                        output_queue_data_text[i]
+                                            .set(&queue_text(output_channels[i].queue_len()), now);
+                                        scroll_text.add_line(&log_str);
+                                        continue;
+                                    }
+                                    // define named bit fields
+                                    // channel def [name:bit name:lo-hi ...]
+                                    "def" => {
+                                        match FieldLayout::parse(&tokens[2..]) {
+                                            Ok(layout) => {
+                                                output_field_layouts[i] = layout;
+                                                output_channel_data_text[i].data_text[4].set(
+                                                    &output_field_layouts[i]
+                                                        .decode(output_channels[i].get()),
+                                                    now,
+                                                );
+                                                let mut log_str: String<PAGE_STR_WIDTH> =
+                                                    String::from_str("Channel ").unwrap();
+                                                write!(log_str, "{} fields defined", i + 1)
+                                                    .unwrap();
+                                                scroll_text.add_line(&log_str);
+                                            }
+                                            Err(e) => scroll_text.add_line(e),
+                                        }
+                                        continue;
+                                    }
+                                    // change current value
+                                    // channel inc/dec/shl/shr [amount]
+                                    "inc" | "dec" | "shl" | "shr" => {
+                                        if num_tokens > 3 {
                                             scroll_text.add_line(
-                                                "Err: 'channel' command needs exactly 1 parameter",
+                                                "Err: 'inc/dec/shl/shr' accept max 1 parameter",
                                             );
                                             continue;
                                         }
-
-                                        // is other a number?
-                                        if let Ok(num) = &tokens[1].trim_end().parse::<i16>() {
-                                            let mut log_str: String<PAGE_STR_WIDTH> =
-                                                String::from_str("Channel ").unwrap();
-                                            log_str.push(((i as u8 + 1) + 0x30) as char).unwrap();
-                                            log_str.push_str(" set to ").unwrap();
-                                            write!(log_str, "{}    ", num).unwrap();
-                                            scroll_text.add_line(&log_str);
-
-                                            output_channels[i].set(*num);
-                                            for format in 0..3 {
-                                                output_channel_data_text[i].data_text[format]
-                                                    .set(num, now);
+                                        let amount = match num_tokens {
+                                            3 => parse_value(&tokens[2]),
+                                            _ => Some(1),
+                                        };
+                                        let Some(amount) = amount else {
+                                            scroll_text.add_line("Err: no valid amount found");
+                                            continue;
+                                        };
+                                        let current = output_channels[i].get();
+                                        match tokens[1].as_str() {
+                                            "inc" => current.wrapping_add(amount),
+                                            "dec" => current.wrapping_sub(amount),
+                                            "shl" => (current as u16)
+                                                .checked_shl(amount as u32)
+                                                .unwrap_or(0)
+                                                as i16,
+                                            _ => (current as u16)
+                                                .checked_shr(amount as u32)
+                                                .unwrap_or(0)
+                                                as i16,
+                                        }
+                                    }
+                                    // channel value
+                                    _ => {
+                                        // named fields
+                                        // channel name=value [name=value ...]
+                                        if tokens[1].contains('=') {
+                                            match output_field_layouts[i]
+                                                .assign(output_channels[i].get(), &tokens[1..])
+                                            {
+                                                Ok(data) => data,
+                                                Err(e) => {
+                                                    scroll_text.add_line(e);
+                                                    continue;
+                                                }
+                                            }
+                                        }
+                                        // bit or bit range
+                                        // channel b3 1 / s3 / c3 / t15 / [7:0] 0x42 / t[7:0]
+                                        else if let Some((op, hi, lo)) = parse_bit_op(&tokens[1])
+                                        {
+                                            let value = match op {
+                                                BitOp::Write => {
+                                                    if num_tokens != 3 {
+                                                        scroll_text.add_line(
+                                                            "Err: bit write needs exactly 1 value",
+                                                        );
+                                                        continue;
+                                                    }
+                                                    parse_value(&tokens[2])
+                                                }
+                                                _ => {
+                                                    if num_tokens != 2 {
+                                                        scroll_text.add_line(
+                                                            "Err: 's/c/t' bit commands do not accept parameters",
+                                                        );
+                                                        continue;
+                                                    }
+                                                    Some(0)
+                                                }
+                                            };
+                                            let Some(value) = value else {
+                                                scroll_text.add_line("no valid 16bit number found");
+                                                continue;
+                                            };
+                                            match apply_bit_op(
+                                                output_channels[i].get(),
+                                                &op,
+                                                hi,
+                                                lo,
+                                                value,
+                                            ) {
+                                                Some(data) => data,
+                                                None => {
+                                                    scroll_text.add_line(
+                                                        "Err: value does not fit into bit range",
+                                                    );
+                                                    continue;
+                                                }
                                             }
                                         } else {
-                                            scroll_text.add_line("no valid 16bit number found");
+                                            if num_tokens != 2 {
+                                                scroll_text.add_line(
+                                                    "Err: 'channel' command needs exactly 1 parameter",
+                                                );
+                                                continue;
+                                            }
+
+                                            // is other a number?
+                                            if let Some(num) = parse_value(&tokens[1]) {
+                                                num
+                                            } else {
+                                                scroll_text.add_line("no valid 16bit number found");
+                                                continue;
+                                            }
                                         }
                                     }
-                                }
+                                };
+
+                                let mut log_str: String<PAGE_STR_WIDTH> =
+                                    String::from_str("Channel ").unwrap();
+                                log_str.push(((i as u8 + 1) + 0x30) as char).unwrap();
+                                log_str.push_str(" set to ").unwrap();
+                                write!(log_str, "{}    ", value).unwrap();
+                                scroll_text.add_line(&log_str);
+
+                                output_channels[i].set(value);
+                                output_channel_data_text[i].show(
+                                    output_channels[i].get(),
+                                    &output_field_layouts[i],
+                                    now,
+                                );
                             }
                         }
                     }
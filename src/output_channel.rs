@@ -1,5 +1,7 @@
 pub mod output_channel {
+    use core::fmt::{Display, Formatter, Result};
     use embedded_hal::digital::OutputPin;
+    use heapless::Deque;
     use rp2040_hal::gpio::{DynPinId, FunctionSioOutput, Pin, PullDown};
 
     //     STATE:  IDLE  (new data)->   PAUSE   DATASET   ENABLESET    PAUSE
@@ -9,6 +11,51 @@ pub mod output_channel {
     //     ENABLE: _______________________________________|‾‾‾‾‾‾‾‾‾‾‾‾|_______
 
     const OUTPUT_TICK_INTERVAL: u64 = 10_000u64; // 10ms tick interval
+    pub const QUEUE_LENGTH: usize = 8; // one digit on the dashboard
+
+    // when the next queued value starts after a word is shifted out, a sync
+    // follower takes it on the next sync edge instead
+    //
+    // The edges of a PIO clock are not seen, they are calculated from the time
+    // it runs (Clock::get_cycles), so on hardware clocks the values follow the
+    // clock only roughly. At most one value starts per main loop pass.
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Pace {
+        Now,
+        Clock(usize), // next rising edge of the clock
+        Delay(u64),   // ticks after the last word
+    }
+
+    impl Pace {
+        // "now", "c1", "c2" or a delay "20ms", "500us", "1s"
+        pub fn parse(text: &str) -> core::result::Result<Self, &'static str> {
+            let (number, scale) = match text {
+                "now" => return Ok(Pace::Now),
+                "c1" => return Ok(Pace::Clock(0)),
+                "c2" => return Ok(Pace::Clock(1)),
+                _ if text.ends_with("ms") => (&text[..text.len() - 2], 1_000),
+                _ if text.ends_with("us") => (&text[..text.len() - 2], 1),
+                _ if text.ends_with('s') => (&text[..text.len() - 1], 1_000_000),
+                _ => return Err("Err: pace needs now, c1, c2 or a delay in s/ms/us"),
+            };
+            let delay = number
+                .parse::<u64>()
+                .ok()
+                .and_then(|n| n.checked_mul(scale))
+                .ok_or("Err: no valid delay found")?;
+            Ok(Pace::Delay(delay))
+        }
+    }
+
+    impl Display for Pace {
+        fn fmt(&self, f: &mut Formatter) -> Result {
+            match self {
+                Pace::Now => write!(f, "next value right away"),
+                Pace::Clock(clock) => write!(f, "next value on clock {}", clock + 1),
+                Pace::Delay(ticks) => write!(f, "next value after {}us", ticks),
+            }
+        }
+    }
 
     enum OutputChannelState {
         Idle,
@@ -17,22 +64,25 @@ pub mod output_channel {
         Pause,
     }
 
-    pub struct OutputChannel {
-        data_pin: Pin<DynPinId, FunctionSioOutput, PullDown>, // data pin
-        enable_pin: Pin<DynPinId, FunctionSioOutput, PullDown>, // enable pin
-        state: OutputChannelState,                            // state of channel
-        data: i16,                                            // number to output
-        bit: u8,                                              // current bit
-        next_tick: u64,                                       // next tick to output
-        reverse: bool,                                        // reverse output bits
-        last: bool, // double output last bit to trigger last shift
+    pub struct OutputChannel<P = Pin<DynPinId, FunctionSioOutput, PullDown>> {
+        data_pin: P,                     // data pin
+        enable_pin: P,                   // enable pin
+        state: OutputChannelState,       // state of channel
+        data: i16,                       // number to output
+        bit: u8,                         // current bit
+        next_tick: u64,                  // next tick to output
+        reverse: bool,                   // reverse output bits
+        word_reverse: bool,              // bit order of the word being shifted
+        last: bool,                      // double output last bit to trigger last shift
+        queue: Deque<i16, QUEUE_LENGTH>, // values to output after this one
+        pub pace: Pace,
+        staged: Option<i16>, // value set on a sync follower, waits for the edge
+        synced: bool,        // sync follower, new values wait for a sync edge
+        latch: bool,         // a sync edge arrived, start the staged or next value
     }
 
-    impl OutputChannel {
-        pub fn new(
-            data_pin: Pin<DynPinId, FunctionSioOutput, PullDown>,
-            enable_pin: Pin<DynPinId, FunctionSioOutput, PullDown>,
-        ) -> Self {
+    impl<P: OutputPin> OutputChannel<P> {
+        pub fn new(data_pin: P, enable_pin: P) -> Self {
             OutputChannel {
                 data_pin,
                 enable_pin,
@@ -41,41 +91,117 @@ pub mod output_channel {
                 bit: 0,
                 next_tick: 0,
                 reverse: false,
+                word_reverse: false,
                 last: false,
+                queue: Deque::new(),
+                pace: Pace::Now,
+                staged: None,
+                synced: false,
+                latch: false,
             }
         }
 
+        // add a value to output after the queued ones, false if the queue is full
+        pub fn queue(&mut self, data: i16) -> bool {
+            self.queue.push_back(data).is_ok()
+        }
+
+        pub fn queue_len(&self) -> usize {
+            self.queue.len()
+        }
+
+        pub fn clear_queue(&mut self) {
+            self.queue.clear();
+        }
+
+        // Restarts the shift, a sync follower waits for the next sync edge.
+        //
+        // There is no double buffered mode: the register has no latch (RCLK)
+        // pin wired, every enable pulse moves its outputs by one bit, so a word
+        // can never be shown at once. Holding a new value back until the word
+        // is done would still pass the outputs through the shifted bits.
         pub fn set(&mut self, data: i16) {
+            if self.synced {
+                self.staged = Some(data);
+                return;
+            }
+            self.start(data);
+        }
+
+        fn start(&mut self, data: i16) {
             self.data = data;
+            self.word_reverse = self.reverse;
             self.bit = 0;
             self.next_tick = 0;
             self.state = OutputChannelState::Pause;
             self.last = false;
         }
 
+        // the value the outputs will show, a staged one if there is one
+        pub fn get(&self) -> i16 {
+            self.staged.unwrap_or(self.data)
+        }
+
+        // the bits are shifted at the same rate on both boards, the sync edge
+        // decides when a word starts
+        pub fn set_synced(&mut self, synced: bool) {
+            self.synced = synced;
+            self.latch = false;
+            if !synced {
+                if let Some(data) = self.staged.take() {
+                    self.start(data);
+                }
+            }
+        }
+
+        // a sync edge, the follower starts the value of the step
+        pub fn latch(&mut self) {
+            self.latch = self.synced;
+        }
+
+        // the value is shifted again in the new order like a new value
         pub fn reverse(&mut self) {
             self.reverse = !self.reverse;
-            self.bit = 0;
-            self.next_tick = 0;
-            self.state = OutputChannelState::Pause;
-            self.last = false;
+            self.set(self.get());
         }
 
         pub fn is_reversed(&self) -> bool {
             self.reverse
         }
 
-        pub fn update(&mut self, now: u64) {
+        // clock_rises: the clocks had a rising edge since the last update,
+        // returns true if a staged or queued value was started,
+        // staged values only exist on a sync follower
+        pub fn update(&mut self, now: u64, clock_rises: [bool; 2]) -> bool {
+            if self.latch {
+                self.latch = false;
+                if let Some(data) = self.staged.take().or_else(|| self.queue.pop_front()) {
+                    self.start(data);
+                    return true;
+                }
+            }
             if now > self.next_tick {
                 match self.state {
-                    OutputChannelState::Idle => {}
+                    // a follower only starts values on the sync edge
+                    OutputChannelState::Idle if self.synced => {}
+                    OutputChannelState::Idle => {
+                        let ready = match self.pace {
+                            Pace::Clock(clock) => clock_rises[clock],
+                            _ => true,
+                        };
+                        if ready {
+                            if let Some(data) = self.queue.pop_front() {
+                                self.set(data);
+                                return true;
+                            }
+                        }
+                    }
                     OutputChannelState::Pause => {
-                        let text_bit: i16;
-                        if self.reverse {
-                            text_bit = 1 << (15 - self.bit);
+                        let text_bit: i16 = if self.word_reverse {
+                            1 << (15 - self.bit)
                         } else {
-                            text_bit = 1 << self.bit;
-                        }
+                            1 << self.bit
+                        };
                         if self.data & text_bit != 0 {
                             self.data_pin.set_high().unwrap();
                         } else {
@@ -96,7 +222,7 @@ pub mod output_channel {
                         if self.bit < 16 {
                             self.state = OutputChannelState::Pause;
                         } else {
-                            if self.last == false {
+                            if !self.last {
                                 self.last = true;
                                 self.bit = 15;
                                 self.next_tick = now + OUTPUT_TICK_INTERVAL;
@@ -104,11 +230,128 @@ pub mod output_channel {
                             } else {
                                 self.state = OutputChannelState::Idle;
                                 self.last = false;
+                                if let Pace::Delay(delay) = self.pace {
+                                    self.next_tick = now + delay;
+                                }
                             }
                         }
                     }
                 }
             }
+            false
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use core::convert::Infallible;
+        use embedded_hal::digital::ErrorType;
+
+        struct NoPin;
+
+        impl ErrorType for NoPin {
+            type Error = Infallible;
+        }
+
+        impl OutputPin for NoPin {
+            fn set_low(&mut self) -> core::result::Result<(), Infallible> {
+                Ok(())
+            }
+
+            fn set_high(&mut self) -> core::result::Result<(), Infallible> {
+                Ok(())
+            }
+        }
+
+        const TICK: u64 = OUTPUT_TICK_INTERVAL + 1;
+
+        // shift until the word is latched, returns the time of the last update
+        fn finish(channel: &mut OutputChannel<NoPin>, mut now: u64) -> u64 {
+            while !matches!(channel.state, OutputChannelState::Idle) {
+                now += TICK;
+                assert!(!channel.update(now, [false; 2]));
+            }
+            now
+        }
+
+        #[test]
+        fn full_queue_refuses_values() {
+            let mut channel = OutputChannel::new(NoPin, NoPin);
+            for value in 0..QUEUE_LENGTH as i16 {
+                assert!(channel.queue(value));
+            }
+            assert!(!channel.queue(99));
+            assert_eq!(channel.queue_len(), QUEUE_LENGTH);
+            channel.clear_queue();
+            assert!(channel.queue(99));
+        }
+
+        #[test]
+        fn queue_waits_for_the_pacing_clock() {
+            let mut channel = OutputChannel::new(NoPin, NoPin);
+            channel.pace = Pace::Clock(1);
+            channel.queue(1);
+            channel.queue(2);
+            let now = finish(&mut channel, 0);
+            assert!(!channel.update(now + TICK, [false; 2]));
+            assert!(!channel.update(now + 2 * TICK, [true, false]));
+            assert!(channel.update(now + 3 * TICK, [false, true]));
+            assert_eq!(channel.get(), 1);
+            let now = finish(&mut channel, now + 3 * TICK);
+            assert!(channel.update(now + TICK, [false, true]));
+            assert_eq!(channel.get(), 2);
+        }
+
+        #[test]
+        fn delay_starts_after_the_latched_word() {
+            let mut channel = OutputChannel::new(NoPin, NoPin);
+            channel.pace = Pace::Delay(1_000);
+            channel.queue(5);
+            let now = finish(&mut channel, 0);
+            assert!(!channel.update(now + 1_000, [false; 2]));
+            assert!(channel.update(now + 1_001, [false; 2]));
+            assert_eq!(channel.get(), 5);
+        }
+
+        #[test]
+        fn follower_starts_values_on_the_sync_edge() {
+            let mut channel = OutputChannel::new(NoPin, NoPin);
+            channel.set_synced(true);
+            channel.queue(2);
+            channel.set(1);
+            assert_eq!((channel.get(), channel.data), (1, 0));
+            let now = finish(&mut channel, 0);
+            assert!(!channel.update(now + TICK, [false, true]));
+            channel.latch();
+            assert!(channel.update(now + 2 * TICK, [false; 2]));
+            assert_eq!(channel.data, 1); // the staged value goes before the queue
+            let now = finish(&mut channel, now + 2 * TICK);
+            assert!(!channel.update(now + TICK, [false; 2]));
+            channel.latch();
+            assert!(channel.update(now + 2 * TICK, [false; 2]));
+            assert_eq!(channel.data, 2);
+        }
+
+        #[test]
+        fn leaving_sync_starts_the_staged_value() {
+            let mut channel = OutputChannel::new(NoPin, NoPin);
+            channel.set_synced(true);
+            channel.set(7);
+            channel.latch(); // ignored once sync is off
+            channel.set_synced(false);
+            assert_eq!((channel.data, channel.bit), (7, 0));
+            assert!(!channel.update(TICK, [false; 2]));
+        }
+
+        #[test]
+        fn reverse_restarts_the_word() {
+            let mut channel = OutputChannel::new(NoPin, NoPin);
+            let now = finish(&mut channel, 0);
+            channel.set(1);
+            channel.update(now + TICK, [false; 2]);
+            channel.reverse();
+            assert_eq!((channel.bit, channel.word_reverse), (0, true));
         }
     }
 }
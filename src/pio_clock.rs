@@ -0,0 +1,130 @@
+pub mod pio_clock {
+    use crate::clock_calc::clock_calc::PioTiming;
+    use pio::{
+        JmpCondition, MovDestination, MovOperation, MovSource, OutDestination, SetDestination,
+    };
+    use rp2040_hal::pac::{PIO0, RESETS};
+    use rp2040_hal::pio::{
+        PIOBuilder, PIOExt, PinDir, Running, ShiftDirection, StateMachine, Tx, ValidStateMachine,
+        PIO, SM0, SM1,
+    };
+
+    // Both clock pins are on PWM slice 5 and could only share one frequency,
+    // so every clock gets its own PIO state machine instead. The state machines
+    // run all the time, the clock decides with the pin function whether the
+    // PIO or the software drives its pin.
+    //
+    //     .wrap_target
+    //         pull noblock     ; new timing or the last one from x
+    //         mov x, osr
+    //         out y, 16        ; high count
+    //         set pins, 1
+    //     high:
+    //         jmp y-- high
+    //         out y, 16        ; low count
+    //         set pins, 0
+    //     low:
+    //         jmp y-- low
+    //     .wrap
+
+    type Clock1Sm = (PIO0, SM0);
+    type Clock2Sm = (PIO0, SM1);
+
+    pub struct PioClocks {
+        _pio: PIO<PIO0>,
+        sm: (
+            StateMachine<Clock1Sm, Running>,
+            StateMachine<Clock2Sm, Running>,
+        ),
+        tx: (Tx<Clock1Sm>, Tx<Clock2Sm>),
+        sm_divider: [(u16, u8); 2],
+        pub sys_clk_hz: u32,
+    }
+
+    impl PioClocks {
+        pub fn new(pio0: PIO0, resets: &mut RESETS, pins: [u8; 2], sys_clk_hz: u32) -> Self {
+            let mut a = pio::Assembler::<32>::new();
+            let mut wrap_target = a.label();
+            let mut wrap_source = a.label();
+            let mut high = a.label();
+            let mut low = a.label();
+            a.bind(&mut wrap_target);
+            a.pull(false, false);
+            a.mov(MovDestination::X, MovOperation::None, MovSource::OSR);
+            a.out(OutDestination::Y, 16);
+            a.set(SetDestination::PINS, 1);
+            a.bind(&mut high);
+            a.jmp(JmpCondition::YDecNonZero, &mut high);
+            a.out(OutDestination::Y, 16);
+            a.set(SetDestination::PINS, 0);
+            a.bind(&mut low);
+            a.jmp(JmpCondition::YDecNonZero, &mut low);
+            a.bind(&mut wrap_source);
+            let program = a.assemble_with_wrap(wrap_source, wrap_target);
+
+            let (mut pio, sm0, sm1, _, _) = pio0.split(resets);
+            let installed = pio.install(&program).unwrap();
+            // SAFETY: both state machines run the same, never uninstalled program
+            let shared = unsafe { installed.share() };
+
+            let (mut sm0, _, mut tx0) = PIOBuilder::from_installed_program(installed)
+                .set_pins(pins[0], 1)
+                .out_shift_direction(ShiftDirection::Right)
+                .build(sm0);
+            sm0.set_pindirs([(pins[0], PinDir::Output)]);
+            let (mut sm1, _, mut tx1) = PIOBuilder::from_installed_program(shared)
+                .set_pins(pins[1], 1)
+                .out_shift_direction(ShiftDirection::Right)
+                .build(sm1);
+            sm1.set_pindirs([(pins[1], PinDir::Output)]);
+
+            // slowest timing until a clock asks for something else
+            tx0.write(u32::MAX);
+            tx1.write(u32::MAX);
+            PioClocks {
+                _pio: pio,
+                sm: (sm0.start(), sm1.start()),
+                tx: (tx0, tx1),
+                sm_divider: [(1, 0); 2],
+                sys_clk_hz,
+            }
+        }
+
+        // takes effect at the start of the next period, or right away if the
+        // divider changes
+        pub fn set_timing(&mut self, clock: usize, timing: &PioTiming) -> Result<(), &'static str> {
+            let divider = (timing.clkdiv_int, timing.clkdiv_frac);
+            let new_divider = (self.sm_divider[clock] != divider).then_some(divider);
+            let written = if clock == 0 {
+                load(&mut self.sm.0, &mut self.tx.0, new_divider, timing.packed())
+            } else {
+                load(&mut self.sm.1, &mut self.tx.1, new_divider, timing.packed())
+            };
+            if !written {
+                return Err("Err: hardware clock did not take the new timing");
+            }
+            self.sm_divider[clock] = divider;
+            Ok(())
+        }
+    }
+
+    // Timing words still queued belong to an older setting, only the newest
+    // one counts. Old words would also run with the wrong divider, so a new
+    // divider restarts the state machine on the new word at once.
+    fn load<SM: ValidStateMachine>(
+        sm: &mut StateMachine<SM, Running>,
+        tx: &mut Tx<SM>,
+        divider: Option<(u16, u8)>,
+        word: u32,
+    ) -> bool {
+        sm.clear_fifos();
+        if !tx.write(word) {
+            return false;
+        }
+        if let Some((int, frac)) = divider {
+            sm.clock_divisor_fixed_point(int, frac);
+            sm.restart();
+        }
+        true
+    }
+}
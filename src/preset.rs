@@ -0,0 +1,125 @@
+pub mod preset {
+    use crate::frequency::frequency::Frequency;
+    use crate::storage::storage::{Persist, Sink, Source, StorageError};
+    use crate::OUTPUT_CHANNELS;
+    use heapless::{String, Vec};
+
+    pub const MAX_PRESETS: usize = 8; // Maximum number of presets
+    pub const PRESET_NAME_WIDTH: usize = 16;
+    const NO_PROGRAM: u8 = u8::MAX; // stored for a deleted program
+
+    // snapshot of all channels, clocks and the selected program
+    pub struct Preset {
+        pub name: String<PRESET_NAME_WIDTH>,
+        pub channel_data: [i16; OUTPUT_CHANNELS],
+        pub channel_reverse: [bool; OUTPUT_CHANNELS],
+        pub clock_auto: [bool; 2],
+        pub clock_freq: [Frequency; 2],
+        pub program: Option<usize>, // None if the program was deleted
+        pub prog_auto: bool,
+        pub prog_freq: Frequency,
+    }
+
+    pub struct PresetStore {
+        presets: Vec<Preset, MAX_PRESETS>,
+    }
+
+    impl PresetStore {
+        pub fn new() -> Self {
+            PresetStore {
+                presets: Vec::new(),
+            }
+        }
+
+        // add preset, replaces a preset with the same name
+        pub fn save(&mut self, preset: Preset) -> Result<(), &'static str> {
+            if let Some(p) = self.presets.iter_mut().find(|p| p.name == preset.name) {
+                *p = preset;
+                Ok(())
+            } else {
+                self.presets
+                    .push(preset)
+                    .map_err(|_| "Err: preset list is full")
+            }
+        }
+
+        pub fn find(&self, name: &str) -> Option<&Preset> {
+            self.presets.iter().find(|p| p.name.as_str() == name)
+        }
+
+        pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+            self.presets.iter()
+        }
+
+        // follow the program numbers that moved down, returns the number of
+        // presets that lost their program
+        pub fn program_deleted(&mut self, program: usize) -> usize {
+            let mut lost = 0;
+            for preset in self.presets.iter_mut() {
+                preset.program = match preset.program {
+                    Some(p) if p == program => {
+                        lost += 1;
+                        None
+                    }
+                    Some(p) if p > program => Some(p - 1),
+                    p => p,
+                };
+            }
+            lost
+        }
+    }
+
+    impl Persist for PresetStore {
+        fn store(&self, w: &mut impl Sink) {
+            w.put_u8(self.presets.len() as u8);
+            for preset in self.presets.iter() {
+                w.put_str(&preset.name);
+                for i in 0..OUTPUT_CHANNELS {
+                    w.put_i16(preset.channel_data[i]);
+                    w.put_bool(preset.channel_reverse[i]);
+                }
+                for i in 0..2 {
+                    w.put_bool(preset.clock_auto[i]);
+                    w.put_u64(preset.clock_freq[i].millihertz());
+                }
+                w.put_u8(preset.program.map_or(NO_PROGRAM, |p| p as u8));
+                w.put_bool(preset.prog_auto);
+                w.put_u64(preset.prog_freq.millihertz());
+            }
+        }
+
+        fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError> {
+            self.presets.clear();
+            for _ in 0..r.get_u8()? {
+                let mut preset = Preset {
+                    name: r.get_str()?,
+                    channel_data: [0; OUTPUT_CHANNELS],
+                    channel_reverse: [false; OUTPUT_CHANNELS],
+                    clock_auto: [false; 2],
+                    clock_freq: [Frequency::from_millihertz(0); 2],
+                    program: None,
+                    prog_auto: false,
+                    prog_freq: Frequency::from_millihertz(0),
+                };
+                for i in 0..OUTPUT_CHANNELS {
+                    preset.channel_data[i] = r.get_i16()?;
+                    preset.channel_reverse[i] = r.get_bool()?;
+                }
+                for i in 0..2 {
+                    preset.clock_auto[i] = r.get_bool()?;
+                    preset.clock_freq[i] = Frequency::from_millihertz(r.get_u64()?);
+                }
+                preset.program = match r.get_u8()? {
+                    NO_PROGRAM => None,
+                    p => Some(p as usize),
+                };
+                preset.prog_auto = r.get_bool()?;
+                preset.prog_freq = Frequency::from_millihertz(r.get_u64()?);
+                self.presets
+                    .push(preset)
+                    .map_err(|_| StorageError::Corrupt)?;
+            }
+            Ok(())
+        }
+    }
+}
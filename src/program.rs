@@ -1,42 +1,360 @@
 pub mod program {
 
-    use crate::button::button::Button;
-    use crate::clock::clock::{Clock, ClockMode};
+    use crate::button::button::{Button, ButtonState};
+    use crate::clock::clock::{Clock, ClockMode, Phase};
+    use crate::clock_calc::clock_calc::{
+        check_freq, plan, ClockSource, MIN_FREQ, SOFTWARE_MAX_FREQ,
+    };
     use crate::format_str;
+    use crate::frequency::frequency::Frequency;
+    use crate::input_channel::input_channel::InputChannel;
+    use crate::output_channel::output_channel::OutputChannel;
+    use crate::pio_clock::pio_clock::PioClocks;
+    use crate::storage::storage::{Persist, Sink, Source, StorageError};
+    use crate::sync::sync::{Sync, SyncMode};
+    use crate::OUTPUT_CHANNELS;
+    use core::fmt::Write;
     use core::str::FromStr;
-    use heapless::{String, Vec};
+    use embedded_hal::digital::{OutputPin, PinState};
+    use heapless::{Deque, String, Vec};
+    use rp2040_hal::gpio::{DynPinId, FunctionSioOutput, Pin, PullDown};
 
     pub const MAX_PROGRAMS: usize = 20; // Maximum number of programs
+    pub const MAX_BURST: u32 = 9999; // pulses, fits the dashboard
+    pub const BURST_ERROR: &str = "Err: burst needs 1-9999 pulses";
+    const _: () = assert!(MAX_BURST == 9999, "update BURST_ERROR to the new limit");
+    pub const MAX_SIGNALS: usize = 6; // clock 1, clock 2 and 4 phase outputs
+    pub const MAX_STEPS: usize = 128; // Maximum number of steps per program
+    pub const MAX_ACTIONS: usize = 32; // Maximum number of step actions per program
+    pub const MAX_LABELS: usize = 16; // Maximum number of step labels per program
+    pub const LABEL_WIDTH: usize = 8;
+    pub const STEP_LOG_LENGTH: usize = 16; // Number of input samples kept
+    pub const PROGRAM_NAME_WIDTH: usize = 10;
     const PAGE_STR_WIDTH: usize = format_str::format_str::PAGE_STR_WIDTH;
+    const SEQUENCE_TEXT_WIDTH: usize = 60; // longer sequences are cut in the log
+
+    // program name padded to the width of the dashboard column
+    pub fn program_name(text: &str) -> Result<String<PAGE_STR_WIDTH>, &'static str> {
+        if text.is_empty() || text.len() > PROGRAM_NAME_WIDTH {
+            return Err("Err: program name needs 1-10 characters");
+        }
+        let mut name: String<PAGE_STR_WIDTH> = String::from_str(text).unwrap();
+        while name.len() < PROGRAM_NAME_WIDTH {
+            name.push(' ').unwrap();
+        }
+        Ok(name)
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum StepTiming {
+        Relative, // durations are shares of the cycle set by the program frequency
+        Micros,   // durations are absolute µs, the program frequency is ignored
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum ActionKind {
+        Put { channel: u8, value: i16 }, // queue a value on an output channel
+        Sample,                          // record the input channel in the step log
+    }
+
+    // something done when a step is entered, besides setting the signals
+    #[derive(Clone, Copy)]
+    pub struct StepAction {
+        pub step: u16,
+        pub kind: ActionKind,
+    }
+
+    // reported by ProgramControl::update when a step was entered
+    pub struct StepEvent {
+        pub step: u16,
+        pub channels: u8,        // bit n = output channel n got a new value
+        pub sample: Option<i16>, // input channel value, if the step samples it
+        pub run_finished: bool,  // last step of a counted run
+        pub one_shot: bool,      // step of a one-shot sequence
+    }
+
     pub struct Program {
         name: String<PAGE_STR_WIDTH>,
-        sequence_length: u8,
-        sequence: [usize; 2],
+        signals: u8,                           // number of signals driven by the program
+        steps: Vec<u8, MAX_STEPS>,             // signal levels per step, bit n = signal n
+        timing: StepTiming,                    // unit of the step durations
+        durations: Vec<u32, MAX_STEPS>,        // duration per step
+        actions: Vec<StepAction, MAX_ACTIONS>, // channel actions, ordered by step
+        labels: Vec<(u16, String<LABEL_WIDTH>), MAX_LABELS>, // phase names, ordered by step
     }
     impl Program {
-        pub fn new(
-            name: String<PAGE_STR_WIDTH>,
-            sequence_length: u8,
-            sequence: [usize; 2],
-        ) -> Self {
-            Program {
+        pub fn new(name: String<PAGE_STR_WIDTH>, signals: u8, steps: Vec<u8, MAX_STEPS>) -> Self {
+            let mut program = Program {
                 name,
-                sequence_length,
-                sequence,
-            }
+                signals,
+                steps,
+                timing: StepTiming::Relative,
+                durations: Vec::new(),
+                actions: Vec::new(),
+                labels: Vec::new(),
+            };
+            program.reset_durations();
+            program
         }
+
+        // all steps get the same share of the cycle
+        fn reset_durations(&mut self) {
+            self.timing = StepTiming::Relative;
+            self.durations.clear();
+            self.durations.resize(self.steps.len(), 1).unwrap();
+        }
+
+        pub fn from_text(name: String<PAGE_STR_WIDTH>, text: &str) -> Result<Self, &'static str> {
+            let (signals, steps) = Program::parse_sequence(text)?;
+            Ok(Program::new(name, signals, steps))
+        }
+
         pub fn get_name(&self) -> &String<PAGE_STR_WIDTH> {
             &self.name
         }
-        pub fn get_sequence_length(&self) -> u8 {
-            self.sequence_length
+        pub fn get_sequence_length(&self) -> u16 {
+            self.steps.len() as u16
+        }
+        pub fn get_signal_count(&self) -> u8 {
+            self.signals
         }
 
-        pub fn get_signals(&self, state: u8) -> [bool; 2] {
-            [
-                self.sequence[0] >> (self.sequence_length - state - 1) & 1 != 0,
-                self.sequence[1] >> (self.sequence_length - state - 1) & 1 != 0,
-            ]
+        // signal levels of a step, bit n = signal n
+        pub fn get_signals(&self, state: u16) -> u8 {
+            self.steps[state as usize]
+        }
+
+        pub fn get_timing(&self) -> StepTiming {
+            self.timing
+        }
+        pub fn get_duration(&self, state: u16) -> u32 {
+            self.durations[state as usize]
+        }
+        pub fn total_duration(&self) -> u64 {
+            self.durations.iter().map(|d| *d as u64).sum()
+        }
+
+        // set durations of all steps, a single value is used for every step
+        pub fn set_durations(
+            &mut self,
+            timing: StepTiming,
+            durations: &[u32],
+        ) -> Result<(), &'static str> {
+            if durations.len() != 1 && durations.len() != self.steps.len() {
+                return Err("Err: need one duration or one per step");
+            }
+            self.timing = timing;
+            for (i, d) in self.durations.iter_mut().enumerate() {
+                *d = if durations.len() == 1 {
+                    durations[0]
+                } else {
+                    durations[i]
+                };
+            }
+            Ok(())
+        }
+
+        // (step, duration) pairs, nothing is changed if one of the steps is invalid
+        pub fn set_step_durations(
+            &mut self,
+            durations: &[(usize, u32)],
+        ) -> Result<(), &'static str> {
+            if durations
+                .iter()
+                .any(|(step, _)| *step >= self.durations.len())
+            {
+                return Err("Err: no valid step number found");
+            }
+            for (step, duration) in durations {
+                self.durations[*step] = *duration;
+            }
+            Ok(())
+        }
+
+        pub fn get_actions(&self, step: u16) -> impl Iterator<Item = &StepAction> {
+            self.actions.iter().filter(move |a| a.step == step)
+        }
+
+        // add an action, a put replaces an earlier put to the same channel in that step
+        pub fn add_action(&mut self, action: StepAction) -> Result<(), &'static str> {
+            if action.step as usize >= self.steps.len() {
+                return Err("Err: no valid step number found");
+            }
+            if let ActionKind::Put { channel, .. } = action.kind {
+                if channel as usize >= OUTPUT_CHANNELS {
+                    return Err("Err: no valid channel number found");
+                }
+            }
+            if let Some(a) = self.actions.iter_mut().find(|a| {
+                a.step == action.step
+                    && match (a.kind, action.kind) {
+                        (
+                            ActionKind::Put { channel: c1, .. },
+                            ActionKind::Put { channel: c2, .. },
+                        ) => c1 == c2,
+                        (ActionKind::Sample, ActionKind::Sample) => true,
+                        _ => false,
+                    }
+            }) {
+                *a = action;
+                return Ok(());
+            }
+            let pos = self
+                .actions
+                .iter()
+                .position(|a| a.step > action.step)
+                .unwrap_or(self.actions.len());
+            self.actions
+                .insert(pos, action)
+                .map_err(|_| "Err: program can have max 32 actions")
+        }
+
+        // remove the actions of one step or of all steps
+        pub fn clear_actions(&mut self, step: Option<u16>) {
+            self.actions.retain(|a| step.is_some_and(|s| a.step != s));
+        }
+
+        // actions as text, e.g. "0:1=0x1234 3:in", cut if too long for the log
+        pub fn get_actions_text(&self) -> String<PAGE_STR_WIDTH> {
+            let mut text: String<PAGE_STR_WIDTH> = String::new();
+            for a in self.actions.iter() {
+                if text.len() >= SEQUENCE_TEXT_WIDTH {
+                    text.push_str("...").unwrap();
+                    break;
+                }
+                match a.kind {
+                    ActionKind::Put { channel, value } => {
+                        write!(text, "{}:{}=0x{:04X} ", a.step, channel + 1, value as u16)
+                    }
+                    ActionKind::Sample => write!(text, "{}:in ", a.step),
+                }
+                .unwrap();
+            }
+            text
+        }
+
+        // label of the phase a step belongs to, set at the phase's first step
+        pub fn get_label(&self, step: u16) -> Option<&str> {
+            self.labels
+                .iter()
+                .rev()
+                .find(|(s, _)| *s <= step)
+                .map(|(_, l)| l.as_str())
+        }
+
+        // set or with None remove the label of a step
+        pub fn set_label(&mut self, step: u16, label: Option<&str>) -> Result<(), &'static str> {
+            if step as usize >= self.steps.len() {
+                return Err("Err: no valid step number found");
+            }
+            self.labels.retain(|(s, _)| *s != step);
+            let Some(label) = label else {
+                return Ok(());
+            };
+            let label =
+                String::try_from(label).map_err(|_| "Err: label can have max 8 characters")?;
+            let pos = self
+                .labels
+                .iter()
+                .position(|(s, _)| *s > step)
+                .unwrap_or(self.labels.len());
+            self.labels
+                .insert(pos, (step, label))
+                .map_err(|_| "Err: program can have max 16 labels")
+        }
+
+        // labels as text, e.g. "0:FETCH 3:EXEC", cut if too long for the log
+        pub fn get_labels_text(&self) -> String<PAGE_STR_WIDTH> {
+            let mut text: String<PAGE_STR_WIDTH> = String::new();
+            for (step, label) in self.labels.iter() {
+                if text.len() >= SEQUENCE_TEXT_WIDTH {
+                    text.push_str("...").unwrap();
+                    break;
+                }
+                write!(text, "{}:{} ", step, label).unwrap();
+            }
+            text
+        }
+
+        // parse durations like "1,4,1,1", zero is not allowed
+        pub fn parse_durations(text: &str) -> Result<Vec<u32, MAX_STEPS>, &'static str> {
+            let mut durations: Vec<u32, MAX_STEPS> = Vec::new();
+            for part in text.split(',') {
+                match part.parse::<u32>() {
+                    Ok(d) if d > 0 => durations
+                        .push(d)
+                        .map_err(|_| "Err: program can have max 128 steps")?,
+                    _ => return Err("Err: durations need to be numbers greater than 0"),
+                }
+            }
+            Ok(durations)
+        }
+
+        // durations as text, e.g. "rel 1,4,1,1", cut if too long for the log
+        pub fn get_timing_text(&self) -> String<PAGE_STR_WIDTH> {
+            let mut text: String<PAGE_STR_WIDTH> = String::from_str(match self.timing {
+                StepTiming::Relative => "rel ",
+                StepTiming::Micros => "us ",
+            })
+            .unwrap();
+            for (i, d) in self.durations.iter().enumerate() {
+                if text.len() >= SEQUENCE_TEXT_WIDTH {
+                    text.push_str("...").unwrap();
+                    break;
+                }
+                if i > 0 {
+                    text.push(',').unwrap();
+                }
+                write!(text, "{}", d).unwrap();
+            }
+            text
+        }
+
+        // parse a sequence like "0111/0010", one group of steps per signal
+        pub fn parse_sequence(text: &str) -> Result<(u8, Vec<u8, MAX_STEPS>), &'static str> {
+            let mut steps: Vec<u8, MAX_STEPS> = Vec::new();
+            let mut signals = 0;
+            for part in text.split('/') {
+                if signals == MAX_SIGNALS {
+                    return Err("Err: program needs 1-6 signals");
+                }
+                if part.is_empty() || part.len() > MAX_STEPS {
+                    return Err("Err: sequence needs 1-128 steps");
+                }
+                if signals == 0 {
+                    steps.resize(part.len(), 0).unwrap();
+                } else if part.len() != steps.len() {
+                    return Err("Err: all signals need the same number of steps");
+                }
+                for (step, c) in steps.iter_mut().zip(part.chars()) {
+                    match c {
+                        '0' => {}
+                        '1' => *step |= 1 << signals,
+                        _ => return Err("Err: sequence may only contain 0 and 1"),
+                    }
+                }
+                signals += 1;
+            }
+            Ok((signals as u8, steps))
+        }
+
+        // sequence as text, e.g. "0111/0010", cut if too long for the log
+        pub fn get_sequence_text(&self) -> String<PAGE_STR_WIDTH> {
+            let mut text: String<PAGE_STR_WIDTH> = String::new();
+            for signal in 0..self.signals {
+                if signal > 0 {
+                    text.push('/').unwrap();
+                }
+                for step in self.steps.iter() {
+                    if text.len() == SEQUENCE_TEXT_WIDTH {
+                        text.push_str("...").unwrap();
+                        return text;
+                    }
+                    text.push(if step >> signal & 1 != 0 { '1' } else { '0' })
+                        .unwrap();
+                }
+            }
+            text
         }
     }
 
@@ -45,42 +363,150 @@ pub mod program {
         Manual,
         OneShot,
         Auto,
+        Run, // counted, see ProgramControl::start_run
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum RunUnit {
+        Cycles, // full sequences
+        Steps,
+    }
+
+    impl Persist for Program {
+        fn store(&self, w: &mut impl Sink) {
+            w.put_str(&self.name);
+            w.put_u8(self.signals);
+            w.put_u16(self.steps.len() as u16);
+            w.put(&self.steps);
+            w.put_bool(self.timing == StepTiming::Micros);
+            for d in self.durations.iter() {
+                w.put_u32(*d);
+            }
+            w.put_u8(self.actions.len() as u8);
+            for a in self.actions.iter() {
+                w.put_u16(a.step);
+                match a.kind {
+                    ActionKind::Put { channel, value } => {
+                        w.put_u8(channel);
+                        w.put_i16(value);
+                    }
+                    ActionKind::Sample => w.put_u8(u8::MAX),
+                }
+            }
+            w.put_u8(self.labels.len() as u8);
+            for (step, label) in self.labels.iter() {
+                w.put_u16(*step);
+                w.put_str(label);
+            }
+        }
+
+        fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError> {
+            self.name = r.get_str()?;
+            self.signals = r.get_u8()?;
+            let length = r.get_u16()? as usize;
+            if self.signals == 0
+                || self.signals as usize > MAX_SIGNALS
+                || length == 0
+                || length > MAX_STEPS
+            {
+                return Err(StorageError::Corrupt);
+            }
+            self.steps.resize(length, 0).unwrap();
+            r.get(&mut self.steps)?;
+            self.timing = if r.get_bool()? {
+                StepTiming::Micros
+            } else {
+                StepTiming::Relative
+            };
+            self.durations.clear();
+            for _ in 0..length {
+                let d = r.get_u32()?;
+                if d == 0 {
+                    return Err(StorageError::Corrupt);
+                }
+                self.durations.push(d).unwrap();
+            }
+            self.actions.clear();
+            for _ in 0..r.get_u8()? {
+                let step = r.get_u16()?;
+                let kind = match r.get_u8()? {
+                    u8::MAX => ActionKind::Sample,
+                    channel => ActionKind::Put {
+                        channel,
+                        value: r.get_i16()?,
+                    },
+                };
+                self.add_action(StepAction { step, kind })
+                    .map_err(|_| StorageError::Corrupt)?;
+            }
+            self.labels.clear();
+            for _ in 0..r.get_u8()? {
+                let step = r.get_u16()?;
+                let label: String<LABEL_WIDTH> = r.get_str()?;
+                self.set_label(step, Some(&label))
+                    .map_err(|_| StorageError::Corrupt)?;
+            }
+            Ok(())
+        }
     }
 
     pub struct ProgramControl {
         program_list: Vec<Program, MAX_PROGRAMS>,
         pub clocks: [Clock; 2],
+        pio_clocks: PioClocks,
+        phase_pins: [Pin<DynPinId, FunctionSioOutput, PullDown>; MAX_SIGNALS - 2], // signals 3..
         buttons: [Button; 2],
         current_program: usize,
         pub mode: ProgramMode,
-        state: u8,
-        pub prog_freq: u32,
+        state: u16,
+        step_pending: bool, // state was changed manually, enter it on the next update
+        run_unit: RunUnit,
+        run_total: u32, // number of cycles or steps to run
+        run_done: u32,
+        step_log: Deque<(u16, i16), STEP_LOG_LENGTH>, // (step, input data)
+        pub prog_freq: Frequency,
         pub sys_freq: u32,
-        ticks_per_step: u32,
-
         next_tick: u64,
+        gate: Option<(u8, bool)>, // input bit and the level that pauses
+        gated: bool,
+        gated_since: u64,
+        pub sync: Sync,
     }
 
     impl ProgramControl {
-        pub fn new(sys_freq: u32, clocks: [Clock; 2], buttons: [Button; 2]) -> Self {
+        pub fn new(
+            sys_freq: u32,
+            clocks: [Clock; 2],
+            pio_clocks: PioClocks,
+            phase_pins: [Pin<DynPinId, FunctionSioOutput, PullDown>; MAX_SIGNALS - 2],
+            buttons: [Button; 2],
+            sync: Sync,
+        ) -> Self {
             let mut p_control = ProgramControl {
                 sys_freq,
                 clocks,
+                pio_clocks,
+                phase_pins,
                 buttons,
+                sync,
                 program_list: Vec::new(),
                 current_program: 0,
                 state: 0,
+                step_pending: false,
+                run_unit: RunUnit::Cycles,
+                run_total: 0,
+                run_done: 0,
+                step_log: Deque::new(),
+                gate: None,
+                gated: false,
+                gated_since: 0,
                 mode: ProgramMode::Manual,
-                prog_freq: 10,
-                ticks_per_step: 1, // dummy value
+                prog_freq: Frequency::from_hertz(1),
                 next_tick: 0,
             };
-            p_control.add_program(Program::new(
-                String::from_str("Manual    ").unwrap(),
-                1,
-                [0b0, 0b0],
-            ));
-            p_control.set_ticks_per_step();
+            p_control.add_program(
+                Program::from_text(String::from_str("Manual    ").unwrap(), "0/0").unwrap(),
+            );
             p_control
         }
 
@@ -95,61 +521,333 @@ pub mod program {
             self.program_list.len()
         }
 
-        pub fn update(&mut self, now: u64) {
+        pub fn get_program(&self, program: usize) -> Option<&Program> {
+            self.program_list.get(program)
+        }
+
+        // programs 1.. can be changed, program 0 is the manual clock mode
+        fn is_user_program(&self, program: usize) -> bool {
+            program > 0 && program < self.program_list.len()
+        }
+
+        pub fn edit_program(
+            &mut self,
+            program: usize,
+            signals: u8,
+            steps: Vec<u8, MAX_STEPS>,
+        ) -> bool {
+            if !self.is_user_program(program) {
+                return false;
+            }
+            let p = &mut self.program_list[program];
+            let keep_durations = p.steps.len() == steps.len();
+            p.signals = signals;
+            p.steps = steps;
+            if !keep_durations {
+                p.reset_durations();
+                let length = p.steps.len() as u16;
+                p.actions.retain(|a| a.step < length);
+                p.labels.retain(|(s, _)| *s < length);
+            }
+            if program == self.current_program {
+                self.set_program(program);
+            }
+            true
+        }
+
+        // add steps to the end of a program, to build sequences longer than a command line
+        pub fn append_program(
+            &mut self,
+            program: usize,
+            signals: u8,
+            steps: Vec<u8, MAX_STEPS>,
+        ) -> Result<(), &'static str> {
+            if !self.is_user_program(program) {
+                return Err("Err: no valid program number found");
+            }
+            let p = &mut self.program_list[program];
+            if p.signals != signals {
+                return Err("Err: appended steps need the same number of signals");
+            }
+            p.steps
+                .extend_from_slice(&steps)
+                .map_err(|_| "Err: program can have max 128 steps")?;
+            // new steps last as long as the previous last step
+            let last = *p.durations.last().unwrap();
+            p.durations.resize(p.steps.len(), last).unwrap();
+            if program == self.current_program {
+                self.set_program(program);
+            }
+            Ok(())
+        }
+
+        // set the durations of all steps, keeps the timing unit if none is given
+        pub fn set_program_durations(
+            &mut self,
+            program: usize,
+            timing: Option<StepTiming>,
+            durations: &[u32],
+        ) -> Result<(), &'static str> {
+            if !self.is_user_program(program) {
+                return Err("Err: no valid program number found");
+            }
+            let p = &mut self.program_list[program];
+            p.set_durations(timing.unwrap_or(p.timing), durations)
+        }
+
+        pub fn set_program_step_durations(
+            &mut self,
+            program: usize,
+            durations: &[(usize, u32)],
+        ) -> Result<(), &'static str> {
+            if !self.is_user_program(program) {
+                return Err("Err: no valid program number found");
+            }
+            self.program_list[program].set_step_durations(durations)
+        }
+
+        pub fn add_program_action(
+            &mut self,
+            program: usize,
+            action: StepAction,
+        ) -> Result<(), &'static str> {
+            if !self.is_user_program(program) {
+                return Err("Err: no valid program number found");
+            }
+            self.program_list[program].add_action(action)
+        }
+
+        pub fn clear_program_actions(
+            &mut self,
+            program: usize,
+            step: Option<u16>,
+        ) -> Result<(), &'static str> {
+            if !self.is_user_program(program) {
+                return Err("Err: no valid program number found");
+            }
+            self.program_list[program].clear_actions(step);
+            Ok(())
+        }
+
+        // input samples taken by program steps, oldest first
+        pub fn get_step_log(&self) -> impl Iterator<Item = &(u16, i16)> + Clone {
+            self.step_log.iter()
+        }
+        pub fn clear_step_log(&mut self) {
+            self.step_log.clear();
+        }
+
+        pub fn set_program_label(
+            &mut self,
+            program: usize,
+            step: u16,
+            label: Option<&str>,
+        ) -> Result<(), &'static str> {
+            if !self.is_user_program(program) {
+                return Err("Err: no valid program number found");
+            }
+            self.program_list[program].set_label(step, label)
+        }
+
+        pub fn rename_program(&mut self, program: usize, name: String<PAGE_STR_WIDTH>) -> bool {
+            if !self.is_user_program(program) {
+                return false;
+            }
+            self.program_list[program].name = name;
+            true
+        }
+
+        pub fn delete_program(&mut self, program: usize) -> bool {
+            if !self.is_user_program(program) {
+                return false;
+            }
+            self.program_list.remove(program);
+            if program == self.current_program {
+                self.set_program(0);
+            } else if program < self.current_program {
+                self.current_program -= 1;
+            }
+            true
+        }
+
+        // advance the program, returns the step entered in this update
+        pub fn update(
+            &mut self,
+            now: u64,
+            output_channels: &mut [OutputChannel; OUTPUT_CHANNELS],
+            input_channel: &InputChannel,
+        ) -> Option<StepEvent> {
             // Update buttons
             let button_states = [self.buttons[0].update(now), self.buttons[1].update(now)];
 
+            // pause auto clocks and running programs while the gate bit is active,
+            // the PIO can not be paused so hardware clocks fall back to software
+            let gated = self
+                .gate
+                .is_some_and(|(bit, level)| (input_channel.data >> bit & 1 != 0) == level);
+            if gated != self.gated {
+                self.gated = gated;
+                if gated {
+                    self.gated_since = now;
+                } else {
+                    self.next_tick += now - self.gated_since;
+                }
+                for clock in 0..self.clocks.len() {
+                    self.update_clock_source(clock).ok();
+                }
+            }
+
+            // a follower takes clock 1 and the steps from the sync input, its
+            // output channels start new values on the same edges
+            let sync_edge = self.sync.poll();
+            let latch = match self.current_program {
+                0 => sync_edge == Some(true),
+                _ => sync_edge.is_some(),
+            };
+            if latch {
+                output_channels.iter_mut().for_each(|c| c.latch());
+            }
+
             // Update clocks
             match self.current_program {
                 0 => {
+                    let synced = sync_edge.map(|level| ButtonState {
+                        state: level,
+                        state_changed: true,
+                        long_press: false,
+                    });
                     for (i, c) in self.clocks.iter_mut().enumerate() {
-                        c.update(now, &button_states[i]);
+                        let button_state = match (i, &synced) {
+                            (0, Some(synced)) => synced,
+                            _ => &button_states[i],
+                        };
+                        c.update(now, button_state, gated);
                     }
+                    self.sync.mirror(self.clocks[0].state);
                 }
                 _ => match self.mode {
                     ProgramMode::Manual => {
+                        // a sync edge is never dropped for a button, the follower
+                        // would stay a step behind
+                        if sync_edge.is_some() {
+                            self.step_forward();
+                        }
+                        // button 2 steps on press like it always did, holding it
+                        // undoes that step and goes one back
                         if button_states[0].state_changed && button_states[0].state {
                             self.mode = ProgramMode::OneShot; // Switch to oneshot mode
                         } else if button_states[1].state_changed && button_states[1].state {
-                            self.state += 1;
-                            if self.state
-                                >= self.program_list[self.current_program].get_sequence_length()
-                            {
-                                self.state = 0;
-                            }
-                            let signals = self.get_signals();
-                            for (i, c) in self.clocks.iter_mut().enumerate() {
-                                c.set_pin(signals[i]);
-                            }
+                            self.step_forward();
+                        } else if button_states[1].long_press {
+                            self.step_back().and_then(|()| self.step_back()).ok();
+                        }
+                        if self.step_pending {
+                            self.step_pending = false;
+                            return Some(self.enter_step(output_channels, input_channel));
                         }
                     }
-                    ProgramMode::Auto | ProgramMode::OneShot => {
-                        if now > self.next_tick {
-                            self.next_tick = now + self.ticks_per_step as u64;
+                    ProgramMode::Auto | ProgramMode::OneShot | ProgramMode::Run => {
+                        let due = match self.sync.mode {
+                            SyncMode::Follower => sync_edge.is_some(),
+                            _ => now > self.next_tick,
+                        };
+                        if due && !gated {
                             self.state += 1;
-                            if self.state
-                                >= self.program_list[self.current_program].get_sequence_length()
-                            {
+                            let wrapped = self.state
+                                >= self.program_list[self.current_program].get_sequence_length();
+                            let one_shot = self.mode == ProgramMode::OneShot;
+                            if wrapped {
                                 self.state = 0;
-                                if self.mode == ProgramMode::OneShot {
+                                if one_shot {
                                     self.mode = ProgramMode::Manual; // Reset to manual mode
                                 }
                             }
-                            let signals = self.get_signals();
-                            for (i, c) in self.clocks.iter_mut().enumerate() {
-                                c.set_pin(signals[i]);
+                            let mut run_finished = false;
+                            if self.mode == ProgramMode::Run
+                                && (wrapped || self.run_unit == RunUnit::Steps)
+                            {
+                                self.run_done += 1;
+                                if self.run_done >= self.run_total {
+                                    self.mode = ProgramMode::Manual;
+                                    run_finished = true;
+                                }
                             }
+                            self.next_tick = now + self.step_ticks(self.state);
+                            let mut event = self.enter_step(output_channels, input_channel);
+                            event.run_finished = run_finished;
+                            event.one_shot = one_shot;
+                            return Some(event);
                         }
                     }
                 },
             }
+            None
         }
 
-        fn get_signals(&self) -> [bool; 2] {
+        // queue channel values, set the signals, then sample the input
+        fn enter_step(
+            &mut self,
+            output_channels: &mut [OutputChannel; OUTPUT_CHANNELS],
+            input_channel: &InputChannel,
+        ) -> StepEvent {
+            self.sync.step();
+            let mut event = StepEvent {
+                step: self.state,
+                channels: 0,
+                sample: None,
+                run_finished: false,
+                one_shot: self.mode == ProgramMode::OneShot,
+            };
+            for action in self.program_list[self.current_program].get_actions(self.state) {
+                match action.kind {
+                    ActionKind::Put { channel, value } => {
+                        output_channels[channel as usize].set(value);
+                        event.channels |= 1 << channel;
+                    }
+                    ActionKind::Sample => event.sample = Some(input_channel.data),
+                }
+            }
+            self.set_outputs();
+            if let Some(data) = event.sample {
+                if self.step_log.is_full() {
+                    self.step_log.pop_front();
+                }
+                self.step_log.push_back((self.state, data)).unwrap();
+            }
+            event
+        }
+
+        fn get_signals(&self) -> u8 {
             if self.current_program > 0 {
                 self.program_list[self.current_program].get_signals(self.state)
             } else {
-                [false, false]
+                0
+            }
+        }
+
+        // duration of a step of the current program in timer ticks
+        fn step_ticks(&self, step: u16) -> u64 {
+            let program = &self.program_list[self.current_program];
+            let duration = program.get_duration(step) as u64;
+            match program.get_timing() {
+                // prog_freq sets the length of the whole cycle
+                StepTiming::Relative => {
+                    self.sys_freq as u64 * 1000 * duration
+                        / (self.prog_freq.millihertz() * program.total_duration())
+                }
+                StepTiming::Micros => self.sys_freq as u64 * duration / 1_000_000,
+            }
+        }
+
+        // drive clock and phase outputs with the signal levels of the current step
+        fn set_outputs(&mut self) {
+            let signals = self.get_signals();
+            for (i, c) in self.clocks.iter_mut().enumerate() {
+                c.set_pin(signals >> i & 1 != 0);
+            }
+            for (i, pin) in self.phase_pins.iter_mut().enumerate() {
+                pin.set_state(PinState::from(signals >> (i + 2) & 1 != 0))
+                    .unwrap();
             }
         }
 
@@ -159,7 +857,9 @@ pub mod program {
                 self.mode = ProgramMode::Manual;
                 self.state = 0;
                 self.next_tick = 0;
-                self.set_ticks_per_step();
+                for clock in 0..self.clocks.len() {
+                    self.update_clock_source(clock).ok();
+                }
                 true
             } else {
                 false
@@ -170,24 +870,45 @@ pub mod program {
             self.current_program
         }
 
+        pub fn get_state(&self) -> u16 {
+            self.state
+        }
+
+        pub fn get_step_label(&self) -> Option<&str> {
+            self.program_list[self.current_program].get_label(self.state)
+        }
+
         pub fn get_current_program_name(&mut self) -> &String<PAGE_STR_WIDTH> {
             &self.program_list[self.current_program].get_name()
         }
 
-        pub fn clocks_sync(&mut self) {
+        // sync and phase shift only work with software clocks
+        fn check_software_clocks(&self) -> Result<(), &'static str> {
+            if self.clocks.iter().any(|c| c.is_hardware()) {
+                return Err("Err: not possible with a hardware clock");
+            }
+            Ok(())
+        }
+
+        pub fn clocks_sync(&mut self) -> Result<(), &'static str> {
+            self.check_software_clocks()?;
             let (clock0, clock1) = self.clocks.split_at_mut(1);
             clock0[0].sync(&mut clock1[0]);
+            Ok(())
         }
-        pub fn clocks_sync_opposite(&mut self) {
+        pub fn clocks_sync_opposite(&mut self) -> Result<(), &'static str> {
+            self.check_software_clocks()?;
             let (clock0, clock1) = self.clocks.split_at_mut(1);
             clock0[0].sync_opposite(&mut clock1[0]);
+            Ok(())
         }
         pub fn clock_toggle_auto(&mut self, clock: usize) {
             if clock < self.clocks.len() {
                 self.clocks[clock].mode = match self.clocks[clock].mode {
                     ClockMode::Manual => ClockMode::Auto,
-                    ClockMode::Auto => ClockMode::Manual,
+                    ClockMode::Auto | ClockMode::Burst => ClockMode::Manual,
                 };
+                self.update_clock_source(clock).ok();
             }
         }
         pub fn clock_set_auto(&mut self, clock: usize, mode: bool) {
@@ -196,24 +917,356 @@ pub mod program {
             } else {
                 ClockMode::Manual
             };
+            self.update_clock_source(clock).ok();
         }
-        pub fn clock_set_freq(&mut self, clock: usize, interval: &u64) {
-            if clock < self.clocks.len() {
-                self.clocks[clock].set_freq(interval);
+        pub fn clock_set_duty(&mut self, clock: usize, duty: u8) -> Result<(), &'static str> {
+            self.clocks[clock].set_duty(duty)?;
+            self.update_clock_source(clock).ok();
+            Ok(())
+        }
+
+        // set the level of a manual clock, the button still works
+        pub fn clock_set_level(&mut self, clock: usize, state: bool) -> Result<(), &'static str> {
+            if !matches!(self.clocks[clock].mode, ClockMode::Manual) {
+                return Err("Err: clock is not in manual mode, use 'c1/2 a'");
             }
+            self.clocks[clock].set_level(state);
+            Ok(())
         }
-        pub fn reset_state(&mut self) {
+
+        // high pulse of a manual clock
+        pub fn clock_pulse(
+            &mut self,
+            clock: usize,
+            micros: u64,
+            now: u64,
+        ) -> Result<(), &'static str> {
+            if micros == 0 {
+                return Err("Err: pulse needs a length in us");
+            }
+            if !matches!(self.clocks[clock].mode, ClockMode::Manual) {
+                return Err("Err: clock is not in manual mode, use 'c1/2 a'");
+            }
+            self.clocks[clock].start_pulse(micros, now);
+            Ok(())
+        }
+
+        // emit pulses at the clock frequency, then stop in manual mode
+        pub fn clock_start_burst(
+            &mut self,
+            clock: usize,
+            pulses: u32,
+            now: u64,
+        ) -> Result<(), &'static str> {
+            if pulses == 0 || pulses > MAX_BURST {
+                return Err(BURST_ERROR);
+            }
+            // pulses are counted in software
+            check_freq(self.pio_clocks.sys_clk_hz, self.clocks[clock].freq, false)?;
+            self.clocks[clock].mode = ClockMode::Burst;
+            self.update_clock_source(clock)?;
+            self.clocks[clock].start_burst(pulses, now);
+            Ok(())
+        }
+
+        // request hardware generation, returns the frequency the clock runs at
+        // in auto mode and if the PIO generates it
+        pub fn clock_set_hardware(
+            &mut self,
+            clock: usize,
+            hardware: bool,
+        ) -> Result<(Frequency, bool), &'static str> {
+            check_freq(
+                self.pio_clocks.sys_clk_hz,
+                self.clocks[clock].freq,
+                hardware,
+            )?;
+            self.clocks[clock].hardware = hardware;
+            self.update_clock_source(clock)?;
+            self.clock_achieved_freq(clock)
+        }
+
+        // frequency the clock runs at in auto mode and if the PIO generates it
+        pub fn clock_achieved_freq(&self, clock: usize) -> Result<(Frequency, bool), &'static str> {
+            let c = &self.clocks[clock];
+            let sys_clk_hz = self.pio_clocks.sys_clk_hz;
+            Ok(match plan(sys_clk_hz, c.freq, c.duty, c.hardware)? {
+                ClockSource::Hardware(timing) => (timing.achieved_freq(sys_clk_hz), true),
+                ClockSource::Software => (c.achieved_freq(), false),
+            })
+        }
+
+        // hardware for an auto clock in the manual clock program if requested
+        // and possible, software otherwise
+        fn update_clock_source(&mut self, clock: usize) -> Result<(), &'static str> {
+            let c = &mut self.clocks[clock];
+            let hardware = c.hardware
+                && self.current_program == 0
+                && matches!(c.mode, ClockMode::Auto)
+                && !self.gated
+                && !(clock == 0 && self.sync.mode == SyncMode::Leader);
+            match plan(self.pio_clocks.sys_clk_hz, c.freq, c.duty, hardware) {
+                Ok(ClockSource::Hardware(timing)) => {
+                    match self.pio_clocks.set_timing(clock, &timing) {
+                        Ok(()) => {
+                            c.use_hardware(Some(timing.achieved_freq(self.pio_clocks.sys_clk_hz)));
+                            Ok(())
+                        }
+                        Err(e) => {
+                            c.use_hardware(None);
+                            Err(e)
+                        }
+                    }
+                }
+                Ok(ClockSource::Software) => {
+                    c.use_hardware(None);
+                    Ok(())
+                }
+                Err(e) => {
+                    c.use_hardware(None);
+                    Err(e)
+                }
+            }
+        }
+
+        // offset clock to the other clock
+        pub fn clock_set_phase(
+            &mut self,
+            clock: usize,
+            phase: &Phase,
+            now: u64,
+        ) -> Result<(), &'static str> {
+            self.check_software_clocks()?;
+            let (clock0, clock1) = self.clocks.split_at_mut(1);
+            if clock == 0 {
+                clock0[0].set_phase(&clock1[0], phase, now)
+            } else {
+                clock1[0].set_phase(&clock0[0], phase, now)
+            }
+        }
+
+        // returns the frequency the clock runs at in auto mode
+        pub fn clock_set_freq(
+            &mut self,
+            clock: usize,
+            freq: Frequency,
+        ) -> Result<Frequency, &'static str> {
+            let c = &mut self.clocks[clock];
+            check_freq(self.pio_clocks.sys_clk_hz, freq, c.hardware)?;
+            c.set_freq(freq);
+            self.update_clock_source(clock)?;
+            Ok(self.clock_achieved_freq(clock)?.0)
+        }
+        pub fn step_forward(&mut self) {
+            let length = self.program_list[self.current_program].get_sequence_length();
+            self.state = (self.state + 1) % length;
+            self.step_pending = true;
+        }
+
+        pub fn step_back(&mut self) -> Result<(), &'static str> {
+            let length = self.program_list[self.current_program].get_sequence_length();
+            let step = (self.state + length - 1) % length;
+            self.check_sync_move(step)?;
+            self.state = step;
+            self.step_pending = true;
+            Ok(())
+        }
+
+        pub fn goto_step(&mut self, step: u16) -> Result<(), &'static str> {
+            if step >= self.program_list[self.current_program].get_sequence_length() {
+                return Err("Err: no valid step number found");
+            }
+            self.check_sync_move(step)?;
+            self.state = step;
+            self.step_pending = true;
+            Ok(())
+        }
+
+        // run the program once from the start, like button 1 does from the
+        // current step
+        pub fn start_one_shot(&mut self) -> Result<(), &'static str> {
+            if self.current_program == 0 {
+                return Err("Err: no program selected for the one shot");
+            }
+            self.reset_state()?;
+            self.mode = ProgramMode::OneShot;
+            Ok(())
+        }
+
+        // run count cycles or steps from the start of the program, then stop
+        pub fn start_run(&mut self, count: u32, unit: RunUnit) -> Result<(), &'static str> {
+            self.reset_state()?;
+            self.run_unit = unit;
+            self.run_total = count;
+            self.run_done = 0;
+            self.mode = ProgramMode::Run;
+            Ok(())
+        }
+
+        // cycles or steps done, cycles or steps to run
+        pub fn get_run_progress(&self) -> (u32, u32, RunUnit) {
+            (self.run_done, self.run_total, self.run_unit)
+        }
+
+        // gate auto clocks and running programs with an input bit, paused while
+        // the bit has level
+        pub fn set_gate(&mut self, gate: Option<(u8, bool)>) {
+            self.gate = gate;
+        }
+
+        pub fn get_gate(&self) -> Option<(u8, bool)> {
+            self.gate
+        }
+
+        pub fn is_gated(&self) -> bool {
+            self.gated
+        }
+
+        // stop auto clocks and running programs, true if something ran
+        pub fn halt(&mut self) -> bool {
+            let mut stopped = false;
+            for clock in 0..self.clocks.len() {
+                if !matches!(self.clocks[clock].mode, ClockMode::Manual) {
+                    self.clock_set_auto(clock, false);
+                    stopped = true;
+                }
+            }
+            if self.mode != ProgramMode::Manual {
+                self.mode = ProgramMode::Manual;
+                stopped = true;
+            }
+            stopped
+        }
+
+        pub fn reset_state(&mut self) -> Result<(), &'static str> {
+            self.check_sync_move(0)?;
             self.state = 0;
             self.next_tick = 0;
+            Ok(())
         }
 
-        pub fn set_freq(&mut self, freq: u32) {
+        // the sync output only announces the next step, a leader can not send
+        // the follower back or home
+        fn check_sync_move(&self, step: u16) -> Result<(), &'static str> {
+            if self.sync.mode == SyncMode::Leader && step != self.state {
+                return Err("Err: a sync leader only steps forward, turn sync off first");
+            }
+            Ok(())
+        }
+
+        // a leader mirrors clock 1 from software, the level of a PIO clock is
+        // not known to the program
+        pub fn set_sync_mode(&mut self, mode: SyncMode) -> Result<(), &'static str> {
+            if mode == SyncMode::Leader {
+                check_freq(self.pio_clocks.sys_clk_hz, self.clocks[0].freq, false)?;
+            }
+            self.sync.set_mode(mode);
+            self.update_clock_source(0)
+        }
+
+        // returns the cycle frequency of the current program after rounding
+        // the steps to timer ticks
+        pub fn set_freq(&mut self, freq: Frequency) -> Result<Frequency, &'static str> {
+            freq.check_range(MIN_FREQ, SOFTWARE_MAX_FREQ)?;
             self.prog_freq = freq;
-            self.set_ticks_per_step();
+            let steps = self.program_list[self.current_program].get_sequence_length();
+            let cycle_ticks = (0..steps).map(|step| self.step_ticks(step)).sum();
+            Ok(Frequency::from_period_ticks(
+                cycle_ticks,
+                self.sys_freq as u64,
+            ))
+        }
+    }
+
+    // settings of a ProgramControl read from storage but not applied yet, so a
+    // record that breaks off halfway leaves the running setup untouched
+    pub struct StoredControl {
+        programs: Vec<Program, MAX_PROGRAMS>,     // user programs
+        clocks: [(bool, Frequency, u8, bool); 2], // auto, freq, duty, hardware
+        prog_freq: Frequency,
+        current_program: usize,
+    }
+
+    impl ProgramControl {
+        pub fn read_stored(&self, r: &mut impl Source) -> Result<StoredControl, StorageError> {
+            let count = r.get_u8()? as usize;
+            if count >= MAX_PROGRAMS {
+                return Err(StorageError::Corrupt);
+            }
+            let mut programs = Vec::new();
+            for _ in 0..count {
+                let mut program = Program::new(String::new(), 1, Vec::new());
+                program.restore(r)?;
+                programs.push(program).ok();
+            }
+            let mut clocks = [(false, MIN_FREQ, 50, false); 2];
+            for clock in clocks.iter_mut() {
+                let auto = r.get_bool()?;
+                let freq = Frequency::from_millihertz(r.get_u64()?);
+                let duty = r.get_u8()?;
+                let hardware = r.get_bool()?;
+                check_freq(self.pio_clocks.sys_clk_hz, freq, hardware)
+                    .map_err(|_| StorageError::Corrupt)?;
+                if duty == 0 || duty >= 100 {
+                    return Err(StorageError::Corrupt);
+                }
+                *clock = (auto, freq, duty, hardware);
+            }
+            let prog_freq = Frequency::from_millihertz(r.get_u64()?);
+            prog_freq
+                .check_range(MIN_FREQ, SOFTWARE_MAX_FREQ)
+                .map_err(|_| StorageError::Corrupt)?;
+            let current_program = r.get_u8()? as usize;
+            if current_program > count {
+                return Err(StorageError::Corrupt);
+            }
+            Ok(StoredControl {
+                programs,
+                clocks,
+                prog_freq,
+                current_program,
+            })
         }
-        pub fn set_ticks_per_step(&mut self) {
-            let steps: u32 = self.program_list[self.current_program].get_sequence_length() as u32;
-            self.ticks_per_step = self.sys_freq / (self.prog_freq / 10 * steps);
+
+        // settings checked by read_stored, nothing in here can fail
+        pub fn apply_stored(&mut self, stored: StoredControl) {
+            self.program_list.truncate(1);
+            for program in stored.programs {
+                self.add_program(program);
+            }
+            for (clock, (auto, freq, duty, hardware)) in stored.clocks.into_iter().enumerate() {
+                self.clocks[clock].hardware = hardware;
+                self.clock_set_auto(clock, auto);
+                self.clock_set_freq(clock, freq).ok();
+                self.clock_set_duty(clock, duty).ok();
+            }
+            self.prog_freq = stored.prog_freq;
+            self.set_program(stored.current_program);
+        }
+    }
+
+    // stores the program list (without program 0), clock settings and the
+    // selected program
+    impl Persist for ProgramControl {
+        fn store(&self, w: &mut impl Sink) {
+            w.put_u8(self.program_list.len() as u8 - 1);
+            for program in self.program_list.iter().skip(1) {
+                program.store(w);
+            }
+            for clock in self.clocks.iter() {
+                w.put_bool(matches!(clock.mode, ClockMode::Auto));
+                w.put_u64(clock.freq.millihertz());
+                w.put_u8(clock.duty);
+                w.put_bool(clock.hardware);
+            }
+            w.put_u64(self.prog_freq.millihertz());
+            w.put_u8(self.current_program as u8);
+        }
+
+        fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError> {
+            let stored = self.read_stored(r)?;
+            self.apply_stored(stored);
+            Ok(())
         }
     }
 }
@@ -0,0 +1,87 @@
+pub mod rp_flash {
+    use crate::storage::storage::{Flash, PAGE_SIZE};
+    use rp2040_hal::rom_data;
+
+    const XIP_BASE: u32 = 0x1000_0000; // flash is mapped here for reading
+    const BLOCK_SIZE: u32 = 65_536; // erase in 64k blocks where possible
+    const BLOCK_ERASE_CMD: u8 = 0xD8;
+
+    // ROM function pointers, looked up while flash is still accessible
+    struct RomFunctions {
+        connect_internal_flash: unsafe extern "C" fn(),
+        flash_exit_xip: unsafe extern "C" fn(),
+        flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+        flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+        flash_flush_cache: unsafe extern "C" fn(),
+        flash_enter_cmd_xip: unsafe extern "C" fn(),
+    }
+
+    // internal flash of the RP2040, accessed through the boot ROM
+    pub struct RpFlash {
+        rom: RomFunctions,
+    }
+
+    impl RpFlash {
+        pub fn new() -> Self {
+            RpFlash {
+                rom: RomFunctions {
+                    connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+                    flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+                    flash_range_erase: rom_data::flash_range_erase::ptr(),
+                    flash_range_program: rom_data::flash_range_program::ptr(),
+                    flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+                    flash_enter_cmd_xip: rom_data::flash_enter_cmd_xip::ptr(),
+                },
+            }
+        }
+    }
+
+    impl Flash for RpFlash {
+        fn read(&self, offset: u32, buf: &mut [u8]) {
+            // SAFETY: the settings region is inside the memory mapped flash
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    (XIP_BASE + offset) as *const u8,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                );
+            }
+        }
+
+        fn erase(&mut self, offset: u32, len: u32) {
+            cortex_m::interrupt::free(|_| {
+                // SAFETY: interrupts are off and the access runs from RAM
+                unsafe { write_flash(&self.rom, true, offset, core::ptr::null(), len as usize) };
+            });
+        }
+
+        fn program(&mut self, offset: u32, data: &[u8; PAGE_SIZE]) {
+            cortex_m::interrupt::free(|_| {
+                // SAFETY: interrupts are off and the access runs from RAM
+                unsafe { write_flash(&self.rom, false, offset, data.as_ptr(), PAGE_SIZE) };
+            });
+        }
+    }
+
+    // Flash can not be read while it is erased or programmed, so this must run
+    // from RAM and only call into the boot ROM (no calls to other functions).
+    #[inline(never)]
+    #[link_section = ".data.ram_func"]
+    unsafe fn write_flash(
+        rom: &RomFunctions,
+        erase: bool,
+        offset: u32,
+        data: *const u8,
+        len: usize,
+    ) {
+        (rom.connect_internal_flash)();
+        (rom.flash_exit_xip)();
+        if erase {
+            (rom.flash_range_erase)(offset, len, BLOCK_SIZE, BLOCK_ERASE_CMD);
+        } else {
+            (rom.flash_range_program)(offset, data, len);
+        }
+        (rom.flash_flush_cache)();
+        (rom.flash_enter_cmd_xip)();
+    }
+}
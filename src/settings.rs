@@ -0,0 +1,62 @@
+pub mod settings {
+    use crate::field_layout::field_layout::FieldLayout;
+    use crate::output_channel::output_channel::OutputChannel;
+    use crate::preset::preset::PresetStore;
+    use crate::program::program::ProgramControl;
+    use crate::storage::storage::{Persist, Sink, Source, StorageError};
+    use crate::OUTPUT_CHANNELS;
+    use core::array::from_fn;
+
+    // everything that survives a power cycle
+    pub struct Settings<'a> {
+        pub prog: &'a mut ProgramControl,
+        pub output_channels: &'a mut [OutputChannel; OUTPUT_CHANNELS],
+        pub output_field_layouts: &'a mut [FieldLayout; OUTPUT_CHANNELS],
+        pub input_field_layout: &'a mut FieldLayout,
+        pub presets: &'a mut PresetStore,
+    }
+
+    impl Persist for Settings<'_> {
+        fn store(&self, w: &mut impl Sink) {
+            self.prog.store(w);
+            for channel in self.output_channels.iter() {
+                w.put_bool(channel.is_reversed());
+            }
+            for layout in self.output_field_layouts.iter() {
+                layout.store(w);
+            }
+            self.input_field_layout.store(w);
+            self.presets.store(w);
+        }
+
+        // everything is read before anything is applied, a broken record
+        // leaves the defaults in place
+        fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError> {
+            let prog = self.prog.read_stored(r)?;
+            let mut channel_reverse = [false; OUTPUT_CHANNELS];
+            for reverse in channel_reverse.iter_mut() {
+                *reverse = r.get_bool()?;
+            }
+            let mut output_field_layouts: [FieldLayout; OUTPUT_CHANNELS] =
+                from_fn(|_| FieldLayout::new());
+            for layout in output_field_layouts.iter_mut() {
+                layout.restore(r)?;
+            }
+            let mut input_field_layout = FieldLayout::new();
+            input_field_layout.restore(r)?;
+            let mut presets = PresetStore::new();
+            presets.restore(r)?;
+
+            self.prog.apply_stored(prog);
+            for (channel, reverse) in self.output_channels.iter_mut().zip(channel_reverse) {
+                if channel.is_reversed() != reverse {
+                    channel.reverse();
+                }
+            }
+            *self.output_field_layouts = output_field_layouts;
+            *self.input_field_layout = input_field_layout;
+            *self.presets = presets;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,442 @@
+pub mod storage {
+    use heapless::String;
+
+    // The settings region is split into slots. Every save goes to the slot after
+    // the current one, so the erase load is spread over the whole region.
+    //
+    //     SLOT:  | header page | payload pages ...          |
+    //
+    //     HEADER: magic  version  sequence  length  crc
+    //
+    // The payload is programmed first and the header page last, a save that is
+    // interrupted leaves the previous slot valid.
+
+    pub const PAGE_SIZE: usize = 256; // smallest programmable unit
+    pub const SECTOR_SIZE: usize = 4096; // smallest erasable unit
+    pub const SETTINGS_VERSION: u16 = 11; // bump when the stored layout changes
+
+    const MAGIC: u32 = 0x4E32_5453; // "N2TS"
+    const HEADER_SIZE: usize = 18;
+
+    // flash backend, offsets are relative to the start of the flash
+    pub trait Flash {
+        fn read(&self, offset: u32, buf: &mut [u8]);
+        fn erase(&mut self, offset: u32, len: u32); // sector aligned
+        fn program(&mut self, offset: u32, data: &[u8; PAGE_SIZE]); // page aligned
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum StorageError {
+        NoData,   // nothing stored yet or version mismatch
+        TooLarge, // settings do not fit into a slot
+        Corrupt,  // stored data can not be decoded
+    }
+
+    // types that can be written to and restored from the settings store
+    pub trait Persist {
+        fn store(&self, w: &mut impl Sink);
+        fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError>;
+    }
+
+    pub trait Sink {
+        fn put(&mut self, bytes: &[u8]);
+
+        fn put_u8(&mut self, v: u8) {
+            self.put(&[v]);
+        }
+        fn put_bool(&mut self, v: bool) {
+            self.put(&[v as u8]);
+        }
+        fn put_u16(&mut self, v: u16) {
+            self.put(&v.to_le_bytes());
+        }
+        fn put_i16(&mut self, v: i16) {
+            self.put_u16(v as u16);
+        }
+        fn put_u32(&mut self, v: u32) {
+            self.put(&v.to_le_bytes());
+        }
+        fn put_u64(&mut self, v: u64) {
+            self.put(&v.to_le_bytes());
+        }
+        fn put_str(&mut self, v: &str) {
+            self.put_u8(v.len() as u8);
+            self.put(v.as_bytes());
+        }
+    }
+
+    pub trait Source {
+        fn get(&mut self, bytes: &mut [u8]) -> Result<(), StorageError>;
+
+        fn get_u8(&mut self) -> Result<u8, StorageError> {
+            let mut b = [0u8; 1];
+            self.get(&mut b)?;
+            Ok(b[0])
+        }
+        fn get_bool(&mut self) -> Result<bool, StorageError> {
+            Ok(self.get_u8()? != 0)
+        }
+        fn get_u16(&mut self) -> Result<u16, StorageError> {
+            let mut b = [0u8; 2];
+            self.get(&mut b)?;
+            Ok(u16::from_le_bytes(b))
+        }
+        fn get_i16(&mut self) -> Result<i16, StorageError> {
+            Ok(self.get_u16()? as i16)
+        }
+        fn get_u32(&mut self) -> Result<u32, StorageError> {
+            let mut b = [0u8; 4];
+            self.get(&mut b)?;
+            Ok(u32::from_le_bytes(b))
+        }
+        fn get_u64(&mut self) -> Result<u64, StorageError> {
+            let mut b = [0u8; 8];
+            self.get(&mut b)?;
+            Ok(u64::from_le_bytes(b))
+        }
+        fn get_str<const N: usize>(&mut self) -> Result<String<N>, StorageError> {
+            let len = self.get_u8()? as usize;
+            let mut b = [0u8; 255];
+            self.get(&mut b[..len])?;
+            let s = core::str::from_utf8(&b[..len]).map_err(|_| StorageError::Corrupt)?;
+            String::try_from(s).map_err(|_| StorageError::Corrupt)
+        }
+    }
+
+    pub fn crc32(mut crc: u32, bytes: &[u8]) -> u32 {
+        crc = !crc;
+        for &b in bytes {
+            crc ^= b as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    // counts and checksums data without writing it
+    struct CrcSink {
+        crc: u32,
+        length: u32,
+    }
+
+    impl Sink for CrcSink {
+        fn put(&mut self, bytes: &[u8]) {
+            self.crc = crc32(self.crc, bytes);
+            self.length += bytes.len() as u32;
+        }
+    }
+
+    // writes data page by page to flash
+    struct FlashSink<'a, F: Flash> {
+        flash: &'a mut F,
+        offset: u32,
+        page: [u8; PAGE_SIZE],
+        fill: usize,
+    }
+
+    impl<F: Flash> FlashSink<'_, F> {
+        fn flush(&mut self) {
+            if self.fill > 0 {
+                self.page[self.fill..].fill(0xFF);
+                self.flash.program(self.offset, &self.page);
+                self.offset += PAGE_SIZE as u32;
+                self.fill = 0;
+            }
+        }
+    }
+
+    impl<F: Flash> Sink for FlashSink<'_, F> {
+        fn put(&mut self, bytes: &[u8]) {
+            for &b in bytes {
+                self.page[self.fill] = b;
+                self.fill += 1;
+                if self.fill == PAGE_SIZE {
+                    self.flush();
+                }
+            }
+        }
+    }
+
+    // reads data from flash, never past the stored length
+    struct FlashSource<'a, F: Flash> {
+        flash: &'a F,
+        offset: u32,
+        end: u32,
+    }
+
+    impl<F: Flash> Source for FlashSource<'_, F> {
+        fn get(&mut self, bytes: &mut [u8]) -> Result<(), StorageError> {
+            if self.offset + bytes.len() as u32 > self.end {
+                return Err(StorageError::Corrupt);
+            }
+            self.flash.read(self.offset, bytes);
+            self.offset += bytes.len() as u32;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct Header {
+        sequence: u32,
+        length: u32,
+        crc: u32,
+    }
+
+    pub struct SettingsStore<F: Flash> {
+        flash: F,
+        base: u32,                      // offset of the settings region
+        slots: u32,                     // number of slots in the region
+        slot_size: u32,                 // sector aligned size of a slot
+        current: Option<(u32, Header)>, // slot and header of the newest settings
+    }
+
+    impl<F: Flash> SettingsStore<F> {
+        pub fn new(flash: F, base: u32, size: u32, slots: u32) -> Self {
+            let mut store = SettingsStore {
+                flash,
+                base,
+                slots,
+                slot_size: size / slots / SECTOR_SIZE as u32 * SECTOR_SIZE as u32,
+                current: None,
+            };
+            store.scan();
+            store
+        }
+
+        // restore the newest valid settings
+        pub fn load(&mut self, item: &mut impl Persist) -> Result<(), StorageError> {
+            let (slot, header) = self.current.ok_or(StorageError::NoData)?;
+            let offset = self.slot_offset(slot) + PAGE_SIZE as u32;
+            let mut source = FlashSource {
+                flash: &self.flash,
+                offset,
+                end: offset + header.length,
+            };
+            item.restore(&mut source)
+        }
+
+        // store settings in the next slot, returns false if nothing changed
+        pub fn save(&mut self, item: &impl Persist) -> Result<bool, StorageError> {
+            let mut crc_sink = CrcSink { crc: 0, length: 0 };
+            item.store(&mut crc_sink);
+            if crc_sink.length > self.slot_size - PAGE_SIZE as u32 {
+                return Err(StorageError::TooLarge);
+            }
+            if let Some((_, header)) = self.current {
+                if header.crc == crc_sink.crc && header.length == crc_sink.length {
+                    return Ok(false);
+                }
+            }
+
+            let (slot, sequence) = match self.current {
+                Some((slot, header)) => ((slot + 1) % self.slots, header.sequence + 1),
+                None => (0, 0),
+            };
+            let offset = self.slot_offset(slot);
+            let used = PAGE_SIZE as u32 + crc_sink.length;
+            let sectors = used.div_ceil(SECTOR_SIZE as u32);
+            self.flash.erase(offset, sectors * SECTOR_SIZE as u32);
+
+            // payload first, header last
+            let mut sink = FlashSink {
+                flash: &mut self.flash,
+                offset: offset + PAGE_SIZE as u32,
+                page: [0xFF; PAGE_SIZE],
+                fill: 0,
+            };
+            item.store(&mut sink);
+            sink.flush();
+
+            let header = Header {
+                sequence,
+                length: crc_sink.length,
+                crc: crc_sink.crc,
+            };
+            let mut page = [0xFF; PAGE_SIZE];
+            page[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+            page[4..6].copy_from_slice(&SETTINGS_VERSION.to_le_bytes());
+            page[6..10].copy_from_slice(&header.sequence.to_le_bytes());
+            page[10..14].copy_from_slice(&header.length.to_le_bytes());
+            page[14..HEADER_SIZE].copy_from_slice(&header.crc.to_le_bytes());
+            self.flash.program(offset, &page);
+
+            self.current = Some((slot, header));
+            Ok(true)
+        }
+
+        // erase the whole region (factory reset)
+        pub fn erase_all(&mut self) {
+            self.flash.erase(self.base, self.slots * self.slot_size);
+            self.current = None;
+        }
+
+        fn slot_offset(&self, slot: u32) -> u32 {
+            self.base + slot * self.slot_size
+        }
+
+        // find the valid slot with the highest sequence number
+        fn scan(&mut self) {
+            self.current = None;
+            for slot in 0..self.slots {
+                let Some(header) = self.read_header(slot) else {
+                    continue;
+                };
+                match self.current {
+                    Some((_, newest)) if newest.sequence >= header.sequence => {}
+                    _ => self.current = Some((slot, header)),
+                }
+            }
+        }
+
+        fn read_header(&self, slot: u32) -> Option<Header> {
+            let offset = self.slot_offset(slot);
+            let mut b = [0u8; HEADER_SIZE];
+            self.flash.read(offset, &mut b);
+            let magic = u32::from_le_bytes(b[0..4].try_into().unwrap());
+            let version = u16::from_le_bytes(b[4..6].try_into().unwrap());
+            let header = Header {
+                sequence: u32::from_le_bytes(b[6..10].try_into().unwrap()),
+                length: u32::from_le_bytes(b[10..14].try_into().unwrap()),
+                crc: u32::from_le_bytes(b[14..HEADER_SIZE].try_into().unwrap()),
+            };
+            if magic != MAGIC
+                || version != SETTINGS_VERSION
+                || header.length > self.slot_size - PAGE_SIZE as u32
+            {
+                return None;
+            }
+
+            // verify payload checksum
+            let mut crc = 0;
+            let mut chunk = [0u8; 64];
+            let mut pos = 0;
+            while pos < header.length {
+                let n = (header.length - pos).min(chunk.len() as u32) as usize;
+                self.flash
+                    .read(offset + PAGE_SIZE as u32 + pos, &mut chunk[..n]);
+                crc = crc32(crc, &chunk[..n]);
+                pos += n as u32;
+            }
+            (crc == header.crc).then_some(header)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::vec;
+        use std::vec::Vec;
+
+        const SLOTS: u32 = 4;
+        const SIZE: u32 = SLOTS * SECTOR_SIZE as u32;
+
+        struct RamFlash(Vec<u8>);
+
+        impl Flash for RamFlash {
+            fn read(&self, offset: u32, buf: &mut [u8]) {
+                let offset = offset as usize;
+                buf.copy_from_slice(&self.0[offset..offset + buf.len()]);
+            }
+            fn erase(&mut self, offset: u32, len: u32) {
+                self.0[offset as usize..(offset + len) as usize].fill(0xFF);
+            }
+            fn program(&mut self, offset: u32, data: &[u8; PAGE_SIZE]) {
+                let offset = offset as usize;
+                self.0[offset..offset + PAGE_SIZE].copy_from_slice(data);
+            }
+        }
+
+        // a counted list of values
+        #[derive(Debug, PartialEq)]
+        struct Record(Vec<u16>);
+
+        impl Persist for Record {
+            fn store(&self, w: &mut impl Sink) {
+                w.put_u8(self.0.len() as u8);
+                for v in self.0.iter() {
+                    w.put_u16(*v);
+                }
+            }
+            fn restore(&mut self, r: &mut impl Source) -> Result<(), StorageError> {
+                let mut values = Vec::new();
+                for _ in 0..r.get_u8()? {
+                    values.push(r.get_u16()?);
+                }
+                self.0 = values;
+                Ok(())
+            }
+        }
+
+        fn new_store() -> SettingsStore<RamFlash> {
+            SettingsStore::new(RamFlash(vec![0xFF; SIZE as usize]), 0, SIZE, SLOTS)
+        }
+
+        // a store on the same flash, like after a power cycle
+        fn reopen(store: SettingsStore<RamFlash>) -> SettingsStore<RamFlash> {
+            SettingsStore::new(store.flash, 0, SIZE, SLOTS)
+        }
+
+        fn load(store: &mut SettingsStore<RamFlash>) -> Result<Record, StorageError> {
+            let mut record = Record(Vec::new());
+            store.load(&mut record).map(|()| record)
+        }
+
+        #[test]
+        fn empty_flash_has_no_data() {
+            assert_eq!(load(&mut new_store()), Err(StorageError::NoData));
+        }
+
+        #[test]
+        fn saves_rotate_through_the_slots() {
+            let mut store = new_store();
+            for i in 0..6u16 {
+                assert_eq!(store.save(&Record(vec![i, i * 3])), Ok(true));
+                assert_eq!(store.current.unwrap().0, i as u32 % SLOTS);
+                store = reopen(store);
+                assert_eq!(load(&mut store), Ok(Record(vec![i, i * 3])));
+            }
+            assert_eq!(store.current.unwrap().1.sequence, 5);
+            assert_eq!(store.save(&Record(vec![5, 15])), Ok(false)); // unchanged
+        }
+
+        #[test]
+        fn crc_mismatch_falls_back_to_the_previous_slot() {
+            let mut store = new_store();
+            store.save(&Record(vec![1])).unwrap();
+            store.save(&Record(vec![2])).unwrap();
+            let payload = store.slot_offset(1) as usize + PAGE_SIZE;
+            store.flash.0[payload + 1] ^= 0x01;
+            let mut store = reopen(store);
+            assert_eq!(load(&mut store), Ok(Record(vec![1])));
+        }
+
+        #[test]
+        fn other_version_is_ignored() {
+            let mut store = new_store();
+            store.save(&Record(vec![1])).unwrap();
+            let version = (SETTINGS_VERSION - 1).to_le_bytes();
+            store.flash.0[4..6].copy_from_slice(&version);
+            let mut store = reopen(store);
+            assert_eq!(load(&mut store), Err(StorageError::NoData));
+        }
+
+        #[test]
+        fn truncated_record_is_corrupt() {
+            let mut store = new_store();
+            store.save(&Record(vec![1, 2, 3])).unwrap();
+            // a valid header for a payload that ends in the middle of the values
+            let length = 4u32;
+            let payload = &store.flash.0[PAGE_SIZE..PAGE_SIZE + length as usize];
+            let crc = crc32(0, payload);
+            store.flash.0[10..14].copy_from_slice(&length.to_le_bytes());
+            store.flash.0[14..HEADER_SIZE].copy_from_slice(&crc.to_le_bytes());
+            let mut store = reopen(store);
+            assert_eq!(load(&mut store), Err(StorageError::Corrupt));
+        }
+    }
+}
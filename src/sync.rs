@@ -0,0 +1,100 @@
+pub mod sync {
+    use core::fmt::{Display, Formatter, Result};
+    use embedded_hal::digital::{InputPin, OutputPin};
+    use rp2040_hal::gpio::{DynPinId, FunctionSioInput, FunctionSioOutput, Pin, PullDown};
+
+    // Couples two boards to drive one circuit, sync out of the leader goes to
+    // sync in of the follower.
+    //
+    //     Prog 0:    sync follows clock 1 of the leader, the follower's
+    //                clock 1 follows sync like it follows button 1
+    //     Prog 1..:  sync toggles on every step the leader enters, the
+    //                follower enters its next step on every edge
+    //
+    // Steps are sent as edges and not as pulses so the follower sees every
+    // step without a pulse length to agree on. The input is not debounced,
+    // it is meant for a short wire between the boards.
+    //
+    // The output channels of the follower start new values on the edges
+    // (rising ones in Prog 0) instead of right away. Only forward steps can
+    // be sent, so a leader refuses back, goto and home, and its clock 1 runs
+    // in software to have a level to mirror.
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum SyncMode {
+        Off,
+        Leader,
+        Follower,
+    }
+
+    pub struct Sync {
+        out_pin: Pin<DynPinId, FunctionSioOutput, PullDown>,
+        in_pin: Pin<DynPinId, FunctionSioInput, PullDown>,
+        pub mode: SyncMode,
+        out_level: bool,
+        in_level: bool,
+    }
+
+    impl Sync {
+        pub fn new(
+            out_pin: Pin<DynPinId, FunctionSioOutput, PullDown>,
+            in_pin: Pin<DynPinId, FunctionSioInput, PullDown>,
+        ) -> Self {
+            Sync {
+                out_pin,
+                in_pin,
+                mode: SyncMode::Off,
+                out_level: false,
+                in_level: false,
+            }
+        }
+
+        pub fn set_mode(&mut self, mode: SyncMode) {
+            self.mode = mode;
+            self.set_out(false);
+            self.in_level = self.in_pin.is_high().unwrap();
+        }
+
+        // leader: copy a clock level to the sync output
+        pub fn mirror(&mut self, level: bool) {
+            if self.mode == SyncMode::Leader && level != self.out_level {
+                self.set_out(level);
+            }
+        }
+
+        // leader: announce one program step
+        pub fn step(&mut self) {
+            if self.mode == SyncMode::Leader {
+                self.set_out(!self.out_level);
+            }
+        }
+
+        // follower: the new level if the sync input changed since the last poll
+        pub fn poll(&mut self) -> Option<bool> {
+            if self.mode != SyncMode::Follower {
+                return None;
+            }
+            let level = self.in_pin.is_high().unwrap();
+            if level == self.in_level {
+                return None;
+            }
+            self.in_level = level;
+            Some(level)
+        }
+
+        fn set_out(&mut self, level: bool) {
+            self.out_level = level;
+            self.out_pin.set_state(level.into()).unwrap();
+        }
+    }
+
+    impl Display for SyncMode {
+        fn fmt(&self, f: &mut Formatter) -> Result {
+            match self {
+                SyncMode::Off => write!(f, "off"),
+                SyncMode::Leader => write!(f, "leader"),
+                SyncMode::Follower => write!(f, "follower"),
+            }
+        }
+    }
+}
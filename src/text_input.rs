@@ -1,8 +1,10 @@
 pub mod text_input {
-    use crate::format_str::format_str::PAGE_STR_WIDTH;
+    use crate::format_str::format_str::{PAGE_STR_WIDTH, PAGE_WIDTH};
     use heapless::String;
 
-    const VALID_CHARS: &str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ -";
+    const VALID_CHARS: &str =
+        "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ -[]:=/,!.%";
+    const MAX_INPUT_LENGTH: usize = PAGE_WIDTH - 12; // rest of the command line
 
     pub enum TextInputState {
         Unchanged,
@@ -29,8 +31,12 @@ pub mod text_input {
                 self.remove_char();
                 self.state = TextInputState::Changed;
             } else if is_valid_char(c as char) {
-                self.text.push(c as char).unwrap();
-                self.state = TextInputState::Changed;
+                if self.text.len() < MAX_INPUT_LENGTH {
+                    self.text.push(c as char).unwrap();
+                    self.state = TextInputState::Changed;
+                } else {
+                    self.state = TextInputState::Unchanged;
+                }
             } else {
                 // Handle invalid character case
             }
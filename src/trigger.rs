@@ -0,0 +1,80 @@
+pub mod trigger {
+    use crate::button::button::Button;
+    use core::fmt::{Display, Formatter, Result};
+
+    // External trigger input, debounced like the clock buttons. An edge runs
+    // the configured action, the trigger stays armed until it is switched off.
+    //
+    //     trigger rise prog            one shot of the current program
+    //     trigger fall c1 burst 16     burst of clock 1
+    //     trigger rise out 2 0x1234    output value held back until the edge
+
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum Edge {
+        Rising,
+        Falling,
+    }
+
+    #[derive(Clone, Copy)]
+    pub enum TriggerAction {
+        OneShot,
+        Burst { clock: usize, pulses: u32 },
+        Output { channel: usize, value: i16 },
+    }
+
+    pub struct Trigger {
+        input: Button,
+        config: Option<(Edge, TriggerAction)>,
+    }
+
+    impl Trigger {
+        pub fn new(input: Button) -> Self {
+            Trigger {
+                input,
+                config: None,
+            }
+        }
+
+        pub fn set(&mut self, config: Option<(Edge, TriggerAction)>) {
+            self.config = config;
+        }
+
+        pub fn get(&self) -> Option<(Edge, TriggerAction)> {
+            self.config
+        }
+
+        // the action to run if the configured edge was seen in this update
+        pub fn update(&mut self, now: u64) -> Option<TriggerAction> {
+            let input = self.input.update(now);
+            let (edge, action) = self.config?;
+            let seen = match edge {
+                Edge::Rising => input.state,
+                Edge::Falling => !input.state,
+            };
+            (input.state_changed && seen).then_some(action)
+        }
+    }
+
+    impl Display for Edge {
+        fn fmt(&self, f: &mut Formatter) -> Result {
+            match self {
+                Edge::Rising => write!(f, "rising"),
+                Edge::Falling => write!(f, "falling"),
+            }
+        }
+    }
+
+    impl Display for TriggerAction {
+        fn fmt(&self, f: &mut Formatter) -> Result {
+            match self {
+                TriggerAction::OneShot => write!(f, "one shot of the program"),
+                TriggerAction::Burst { clock, pulses } => {
+                    write!(f, "burst of {} pulses on clock {}", pulses, clock + 1)
+                }
+                TriggerAction::Output { channel, value } => {
+                    write!(f, "0x{:04X} to output {}", *value as u16, channel + 1)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+pub mod wavedrom {
+    use crate::program::program::{ActionKind, Program, MAX_SIGNALS, MAX_STEPS};
+    use crate::OUTPUT_CHANNELS;
+    use core::fmt::{Result, Write};
+    use heapless::{String, Vec};
+
+    // WaveDrom signal descriptions, as used in the course handouts
+    //
+    //     {signal: [{name: "clk1", wave: "01.0"}, {name: "clk2", wave: "0.10"}]}
+    //
+    // Every wave character is one program step: 0/l/L low, 1/h/H high and '.'
+    // repeats the previous character. Clock characters p/n/P/N are a full
+    // period, so if a wave uses them every character counts as two steps.
+    // Data lanes ('=', '2'-'9') can not drive a signal and are skipped.
+
+    pub const IMPORT_LENGTH: usize = 2048; // max size of a pasted description
+    pub const SIGNAL_NAMES: [&str; MAX_SIGNALS] = ["clk1", "clk2", "ph1", "ph2", "ph3", "ph4"];
+
+    pub enum ImportState {
+        Collecting,
+        Done,
+        Overflow,
+    }
+
+    // collects a pasted description until its outer braces are closed
+    pub struct WaveImport {
+        text: String<IMPORT_LENGTH>,
+        depth: u8,
+    }
+
+    impl WaveImport {
+        pub fn new() -> Self {
+            WaveImport {
+                text: String::new(),
+                depth: 0,
+            }
+        }
+
+        pub fn clear(&mut self) {
+            self.text.clear();
+            self.depth = 0;
+        }
+
+        pub fn add_char(&mut self, c: u8) -> ImportState {
+            if self.text.is_empty() && c != b'{' {
+                return ImportState::Collecting; // skip anything before the first brace
+            }
+            match c {
+                b'{' => self.depth += 1,
+                b'}' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+            let c = if c.is_ascii_graphic() { c as char } else { ' ' }; // line breaks, tabs
+            if self.text.push(c).is_err() {
+                return ImportState::Overflow;
+            }
+            if self.depth == 0 {
+                ImportState::Done
+            } else {
+                ImportState::Collecting
+            }
+        }
+
+        pub fn get_text(&self) -> &str {
+            &self.text
+        }
+    }
+
+    // find the quoted values of all "wave" keys
+    fn find_waves(text: &str) -> core::result::Result<Vec<&str, MAX_SIGNALS>, &'static str> {
+        let mut waves: Vec<&str, MAX_SIGNALS> = Vec::new();
+        let mut rest = text;
+        while let Some(pos) = rest.find("wave") {
+            rest = &rest[pos + 4..];
+            let Some(value) = rest
+                .trim_start_matches(['"', '\'', ' '])
+                .strip_prefix(':')
+                .map(|v| v.trim_start())
+            else {
+                continue; // "wave" inside a name or text
+            };
+            let Some(quote @ ('"' | '\'')) = value.chars().next() else {
+                continue;
+            };
+            let Some(end) = value[1..].find(quote) else {
+                return Err("Err: wave text is not closed");
+            };
+            let wave = &value[1..end + 1];
+            rest = &value[end + 2..];
+            if wave.contains(|c: char| c == '=' || ('2'..='9').contains(&c)) {
+                continue; // data lane
+            }
+            waves
+                .push(wave)
+                .map_err(|_| "Err: program needs 1-6 signals")?;
+        }
+        if waves.is_empty() {
+            return Err("Err: no wave found");
+        }
+        Ok(waves)
+    }
+
+    // parse a WaveDrom description into signals and steps like Program::parse_sequence
+    pub fn parse(text: &str) -> core::result::Result<(u8, Vec<u8, MAX_STEPS>), &'static str> {
+        let waves = find_waves(text)?;
+        let clock = waves.iter().any(|w| w.contains(['p', 'n', 'P', 'N']));
+        let mut levels: Vec<Vec<bool, MAX_STEPS>, MAX_SIGNALS> = Vec::new();
+        for wave in waves.iter() {
+            let mut signal: Vec<bool, MAX_STEPS> = Vec::new();
+            let mut previous = [false, false];
+            for c in wave.chars() {
+                let period = match c {
+                    '0' | 'l' | 'L' | 'd' | 'x' | 'z' => [false, false],
+                    '1' | 'h' | 'H' | 'u' => [true, true],
+                    'p' | 'P' => [true, false],
+                    'n' | 'N' => [false, true],
+                    '.' | '|' => previous,
+                    ' ' => continue,
+                    _ => return Err("Err: unknown wave character"),
+                };
+                previous = period;
+                for level in period.iter().take(if clock { 2 } else { 1 }) {
+                    signal
+                        .push(*level)
+                        .map_err(|_| "Err: program can have max 128 steps")?;
+                }
+            }
+            if signal.is_empty() {
+                return Err("Err: empty wave found");
+            }
+            levels.push(signal).unwrap();
+        }
+
+        // shorter waves keep their last level
+        let length = levels.iter().map(|l| l.len()).max().unwrap();
+        let mut steps: Vec<u8, MAX_STEPS> = Vec::new();
+        for step in 0..length {
+            let mut bits = 0;
+            for (signal, l) in levels.iter().enumerate() {
+                if *l.get(step).unwrap_or(l.last().unwrap()) {
+                    bits |= 1 << signal;
+                }
+            }
+            steps.push(bits).unwrap();
+        }
+        Ok((waves.len() as u8, steps))
+    }
+
+    // wave text of a single signal, repeated levels as '.'
+    fn write_wave(w: &mut impl Write, program: &Program, signal: u8) -> Result {
+        let mut previous = None;
+        for state in 0..program.get_sequence_length() {
+            let level = program.get_signals(state) >> signal & 1 != 0;
+            w.write_char(match (previous == Some(level), level) {
+                (true, _) => '.',
+                (false, true) => '1',
+                (false, false) => '0',
+            })?;
+            previous = Some(level);
+        }
+        Ok(())
+    }
+
+    pub fn export_program(w: &mut impl Write, program: &Program) -> Result {
+        write!(w, "{{signal: [\r\n")?;
+        for signal in 0..program.get_signal_count() {
+            write!(
+                w,
+                "  {{name: \"{}\", wave: \"",
+                SIGNAL_NAMES[signal as usize]
+            )?;
+            write_wave(w, program, signal)?;
+            write!(w, "\"}},\r\n")?;
+        }
+
+        // output channel values and input samples as data lanes
+        for lane in 0..=OUTPUT_CHANNELS {
+            let wanted = |kind: &ActionKind| match kind {
+                ActionKind::Put { channel, .. } => *channel as usize == lane,
+                ActionKind::Sample => lane == OUTPUT_CHANNELS,
+            };
+            let steps = program.get_sequence_length();
+            if !(0..steps).any(|s| program.get_actions(s).any(|a| wanted(&a.kind))) {
+                continue;
+            }
+            if lane == OUTPUT_CHANNELS {
+                write!(w, "  {{name: \"in\", wave: \"")?;
+            } else {
+                write!(w, "  {{name: \"ch{}\", wave: \"", lane + 1)?;
+            }
+            let mut started = false;
+            for s in 0..steps {
+                let c = match program.get_actions(s).any(|a| wanted(&a.kind)) {
+                    true => '=',
+                    false if lane == OUTPUT_CHANNELS || !started => 'x',
+                    false => '.',
+                };
+                started |= c == '=';
+                w.write_char(c)?;
+            }
+            write!(w, "\", data: [")?;
+            for s in 0..steps {
+                for a in program.get_actions(s).filter(|a| wanted(&a.kind)) {
+                    match a.kind {
+                        ActionKind::Put { value, .. } => write!(w, "\"0x{:04X}\", ", value as u16)?,
+                        ActionKind::Sample => write!(w, "\"read\", ")?,
+                    }
+                }
+            }
+            write!(w, "]}},\r\n")?;
+        }
+        write!(
+            w,
+            "], head: {{text: \"{}\"}}}}\r\n",
+            program.get_name().trim_end()
+        )
+    }
+
+    // recorded input samples as data lanes, oldest first
+    pub fn export_log<'a>(
+        w: &mut impl Write,
+        log: impl Iterator<Item = &'a (u16, i16)> + Clone,
+    ) -> Result {
+        write!(w, "{{signal: [\r\n  {{name: \"step\", wave: \"")?;
+        for _ in log.clone() {
+            w.write_char('=')?;
+        }
+        write!(w, "\", data: [")?;
+        for (step, _) in log.clone() {
+            write!(w, "\"{}\", ", step)?;
+        }
+        write!(w, "]}},\r\n  {{name: \"in\", wave: \"")?;
+        for _ in log.clone() {
+            w.write_char('=')?;
+        }
+        write!(w, "\", data: [")?;
+        for (_, data) in log {
+            write!(w, "\"0x{:04X}\", ", *data as u16)?;
+        }
+        write!(w, "]}},\r\n], head: {{text: \"Step log\"}}}}\r\n")
+    }
+}
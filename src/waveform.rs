@@ -0,0 +1,106 @@
+pub mod waveform {
+    use crate::program::program::{Program, MAX_SIGNALS};
+    use crate::wavedrom::wavedrom::SIGNAL_NAMES;
+    use core::fmt::{Result, Write};
+
+    // Timing diagram of the active program, one row per signal and a cursor row
+    //
+    //     clk1  ▁▁╱▔╲▁╱▔╲▁
+    //     clk2  ▁▁▁▁╱▔▔▔╲▁
+    //             ^ step 1/5
+    //
+    // Every step is two columns wide, the first one shows the edge into the step.
+    // Long programs are shown in pages of WAVE_STEPS steps around the cursor.
+
+    pub const WAVE_ROWS: u8 = MAX_SIGNALS as u8 + 1; // signals and cursor row
+    pub const WAVE_STEPS: u16 = 52; // steps shown at once
+    const LABEL_WIDTH: u8 = 6;
+
+    const LOW: char = '▁';
+    const HIGH: char = '▔';
+    const RISING: char = '╱';
+    const FALLING: char = '╲';
+
+    static INVERTED_ON: &str = "\x1B[7m";
+    static INVERTED_OFF: &str = "\x1B[27m";
+    static CLEAR_LINE: &str = "\x1B[K";
+
+    // draw one row of the waveform of a program with the cursor at state, top
+    // left of the waveform at x/y; the main loop draws a row per pass so a
+    // redraw never blocks it for long
+    pub fn write_waveform_row(
+        w: &mut impl Write,
+        program: Option<&Program>,
+        state: u16,
+        x: u8,
+        y: u8,
+        row: u8,
+    ) -> Result {
+        let Some(program) = program else {
+            // program 0 has no sequence, the clocks run on their own
+            write!(w, "\x1B[{};{}H", y + row, x)?;
+            if row == 0 {
+                w.write_str("  no program waveform in manual clock mode")?;
+            }
+            return w.write_str(CLEAR_LINE);
+        };
+        let length = program.get_sequence_length();
+        let first = state / WAVE_STEPS * WAVE_STEPS;
+        let last = (first + WAVE_STEPS).min(length);
+
+        if row < MAX_SIGNALS as u8 {
+            let signal = row;
+            write!(w, "\x1B[{};{}H", y + signal, x)?;
+            if signal < program.get_signal_count() {
+                write!(
+                    w,
+                    "{:<width$}",
+                    SIGNAL_NAMES[signal as usize],
+                    width = LABEL_WIDTH as usize
+                )?;
+                let mut previous = if first > 0 {
+                    program.get_signals(first - 1) >> signal & 1 != 0
+                } else {
+                    program.get_signals(length - 1) >> signal & 1 != 0 // wraps around
+                };
+                for step in first..last {
+                    let level = program.get_signals(step) >> signal & 1 != 0;
+                    let edge = match (previous, level) {
+                        (false, true) => RISING,
+                        (true, false) => FALLING,
+                        (_, true) => HIGH,
+                        (_, false) => LOW,
+                    };
+                    let fill = if level { HIGH } else { LOW };
+                    if step == state {
+                        write!(w, "{}{}{}{}", INVERTED_ON, edge, fill, INVERTED_OFF)?;
+                    } else {
+                        write!(w, "{}{}", edge, fill)?;
+                    }
+                    previous = level;
+                }
+            }
+            return w.write_str(CLEAR_LINE);
+        }
+
+        // cursor row
+        let column = x + LABEL_WIDTH + ((state - first) * 2) as u8;
+        write!(
+            w,
+            "\x1B[{};{}H{}\x1B[{}G^ step {}/{}",
+            y + MAX_SIGNALS as u8,
+            x,
+            CLEAR_LINE,
+            column,
+            state,
+            length
+        )?;
+        if let Some(label) = program.get_label(state) {
+            write!(w, " {}", label)?;
+        }
+        if first > 0 || last < length {
+            write!(w, " (steps {}-{})", first, last - 1)?;
+        }
+        Ok(())
+    }
+}